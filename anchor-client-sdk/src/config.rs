@@ -0,0 +1,190 @@
+use std::{env, str::FromStr, time::Duration};
+
+use anchor_client::{
+    Cluster,
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey},
+};
+use anyhow::{Result, anyhow};
+use solana_client::nonblocking::rpc_client::RpcClient;
+
+use crate::predix_program;
+
+const DEFAULT_RPC_URL: &str = "https://api.devnet.solana.com";
+const DEFAULT_WS_URL: &str = "wss://api.devnet.solana.com/";
+
+/// Cluster and program settings shared by the api, the event listener and the SDK.
+#[derive(Debug, Clone)]
+pub struct PredixConfig {
+    pub rpc_url: String,
+    pub ws_url: String,
+    pub program_id: Pubkey,
+    pub commitment: CommitmentConfig,
+    pub rpc_timeout: Duration,
+    pub confirm_timeout: Duration,
+    pub signer_timeout: Duration,
+    /// Durable nonce accounts (authority: the fee payer) for user co-signed
+    /// transactions. Empty means those transactions use a recent blockhash.
+    pub nonce_accounts: Vec<Pubkey>,
+    /// How long a durable-nonce transaction may wait for the user's signature
+    /// before the backend advances the nonce and reuses the account.
+    pub nonce_lease: Duration,
+}
+
+impl PredixConfig {
+    pub fn builder() -> PredixConfigBuilder {
+        PredixConfigBuilder::default()
+    }
+
+    /// Reads the config from the environment:
+    /// `SOLANA_RPC_URL`, `SOLANA_WS_RPC_URL`, `PROGRAM_ID`, `SOLANA_COMMITMENT`,
+    /// `RPC_TIMEOUT_SECS`, `CONFIRM_TIMEOUT_SECS`, `SIGNER_TIMEOUT_SECS`,
+    /// `NONCE_ACCOUNTS` (comma separated) and `NONCE_LEASE_SECS`.
+    /// Anything unset or empty falls back to the builder defaults (devnet,
+    /// confirmed).
+    pub fn from_env() -> Result<Self> {
+        let mut builder = Self::builder();
+        if let Some(url) = env_var("SOLANA_RPC_URL") {
+            builder = builder.cluster_url(url);
+        }
+        if let Some(url) = env_var("SOLANA_WS_RPC_URL") {
+            builder = builder.ws_url(url);
+        }
+        if let Some(program_id) = env_var("PROGRAM_ID") {
+            let program_id =
+                Pubkey::from_str(&program_id).map_err(|e| anyhow!("Invalid PROGRAM_ID: {}", e))?;
+            builder = builder.program_id(program_id);
+        }
+        if let Some(commitment) = env_var("SOLANA_COMMITMENT") {
+            let commitment = CommitmentConfig::from_str(&commitment)
+                .map_err(|e| anyhow!("Invalid SOLANA_COMMITMENT: {}", e))?;
+            builder = builder.commitment(commitment);
+        }
+        if let Some(secs) = env_secs("RPC_TIMEOUT_SECS")? {
+            builder = builder.rpc_timeout(secs);
+        }
+        if let Some(secs) = env_secs("CONFIRM_TIMEOUT_SECS")? {
+            builder = builder.confirm_timeout(secs);
+        }
+        if let Some(secs) = env_secs("SIGNER_TIMEOUT_SECS")? {
+            builder = builder.signer_timeout(secs);
+        }
+        if let Some(accounts) = env_var("NONCE_ACCOUNTS") {
+            let accounts = accounts
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(Pubkey::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow!("Invalid NONCE_ACCOUNTS: {}", e))?;
+            builder = builder.nonce_accounts(accounts);
+        }
+        if let Some(secs) = env_secs("NONCE_LEASE_SECS")? {
+            builder = builder.nonce_lease(secs);
+        }
+        Ok(builder.build())
+    }
+
+    pub fn cluster(&self) -> Cluster {
+        Cluster::Custom(self.rpc_url.clone(), self.ws_url.clone())
+    }
+
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new_with_timeouts_and_commitment(
+            self.rpc_url.clone(),
+            self.rpc_timeout,
+            self.commitment,
+            self.confirm_timeout,
+        )
+    }
+}
+
+/// A set, non-empty environment variable; `FOO=` counts as unset.
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn env_secs(name: &str) -> Result<Option<Duration>> {
+    match env_var(name) {
+        Some(v) => {
+            let secs = v
+                .parse::<u64>()
+                .map_err(|e| anyhow!("Invalid {}: {}", name, e))?;
+            Ok(Some(Duration::from_secs(secs)))
+        }
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PredixConfigBuilder {
+    config: PredixConfig,
+}
+
+impl Default for PredixConfigBuilder {
+    fn default() -> Self {
+        Self {
+            config: PredixConfig {
+                rpc_url: DEFAULT_RPC_URL.to_string(),
+                ws_url: DEFAULT_WS_URL.to_string(),
+                program_id: predix_program::ID,
+                commitment: CommitmentConfig::confirmed(),
+                rpc_timeout: Duration::from_secs(30),
+                confirm_timeout: Duration::from_secs(60),
+                signer_timeout: Duration::from_secs(10),
+                nonce_accounts: Vec::new(),
+                nonce_lease: Duration::from_secs(2 * 60),
+            },
+        }
+    }
+}
+
+impl PredixConfigBuilder {
+    pub fn cluster_url(mut self, url: impl Into<String>) -> Self {
+        self.config.rpc_url = url.into();
+        self
+    }
+
+    pub fn ws_url(mut self, url: impl Into<String>) -> Self {
+        self.config.ws_url = url.into();
+        self
+    }
+
+    pub fn program_id(mut self, program_id: Pubkey) -> Self {
+        self.config.program_id = program_id;
+        self
+    }
+
+    pub fn commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.config.commitment = commitment;
+        self
+    }
+
+    pub fn rpc_timeout(mut self, timeout: Duration) -> Self {
+        self.config.rpc_timeout = timeout;
+        self
+    }
+
+    pub fn confirm_timeout(mut self, timeout: Duration) -> Self {
+        self.config.confirm_timeout = timeout;
+        self
+    }
+
+    pub fn signer_timeout(mut self, timeout: Duration) -> Self {
+        self.config.signer_timeout = timeout;
+        self
+    }
+
+    pub fn nonce_accounts(mut self, accounts: Vec<Pubkey>) -> Self {
+        self.config.nonce_accounts = accounts;
+        self
+    }
+
+    pub fn nonce_lease(mut self, lease: Duration) -> Self {
+        self.config.nonce_lease = lease;
+        self
+    }
+
+    pub fn build(self) -> PredixConfig {
+        self.config
+    }
+}
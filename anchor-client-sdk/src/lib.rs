@@ -1,71 +1,338 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anchor_client::{
-    Client, Cluster, Program,
-    solana_sdk::{
-        commitment_config::CommitmentConfig, instruction::Instruction, signature::Keypair,
-        signer::Signer,
-    },
+    Client, Program,
+    solana_sdk::{hash::Hash, instruction::Instruction, signature::Signature, signer::Signer},
 };
 use anchor_lang::{
-    declare_program,
+    AccountDeserialize, declare_program,
     prelude::{AccountMeta, Pubkey, system_program},
 };
 use anyhow::{Ok, Result};
-use solana_sdk::{message::Message, transaction::Transaction};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{message::Message, system_instruction, transaction::Transaction};
 use uuid::Uuid;
 
 use crate::predix_program::types::MarketOutcome;
 pub use crate::{
+    config::{PredixConfig, PredixConfigBuilder},
+    nonce::NoncePool,
     predix_program::{
+        accounts::Market as MarketAccount,
         client::{accounts, args},
         types::MatchFill,
     },
+    signer::{SignerHandle, TransactionSigner},
     utils::{
         derive_market_pda, derive_user_collateral_ata_pda, derive_yes_and_no_ata_pdas,
         derive_yes_and_no_mint_pdas, vault_pda,
     },
 };
 
+pub mod config;
+pub mod nonce;
+pub mod signer;
 pub mod utils;
 
 declare_program!(predix_program);
 
-pub struct PredixSdk {
-    keypair: Arc<Keypair>,
-    program: Program<Arc<Keypair>>,
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A transaction built and fee-payer signed by the SDK.
+#[derive(Debug, Clone)]
+pub struct BuiltTx {
+    pub signature: Signature,
+    /// bincode + base64 serialized transaction, as handed to wallets.
+    pub tx_base64: String,
+    /// Blockhash the transaction was built on; the stored nonce value for
+    /// durable-nonce transactions.
+    pub recent_blockhash: Hash,
+    /// Block height after which the transaction's blockhash is no longer valid.
+    /// Unused (0) for durable-nonce transactions, which do not expire.
+    pub last_valid_block_height: u64,
+    /// Durable nonce account the transaction is built on, if any.
+    pub nonce_account: Option<Pubkey>,
 }
 
-impl PredixSdk {
-    pub fn new(payer_private_key: &str) -> Result<Self> {
-        let key_pair = Keypair::from_base58_string(payer_private_key);
-        let keypair_arc = Arc::new(key_pair);
+/// Signs `tx` on the blocking pool: a signer may be a remote service that
+/// does synchronous network I/O, which must not stall the async runtime.
+async fn sign_tx(
+    mut tx: Transaction,
+    signers: Vec<Arc<SignerHandle>>,
+    recent_blockhash: Hash,
+) -> Result<Transaction> {
+    tokio::task::spawn_blocking(move || {
+        let signers = signers.iter().map(|s| s.as_ref()).collect::<Vec<_>>();
+        tx.try_partial_sign(&signers[..], recent_blockhash)?;
+        Ok(tx)
+    })
+    .await?
+}
 
-        let provider = Client::new_with_options(
-            Cluster::Devnet,
-            keypair_arc.clone(),
-            CommitmentConfig::confirmed(),
-        );
+fn encode_tx(tx: &Transaction) -> Result<String> {
+    let serialized = bincode::serialize(tx)?;
+    #[allow(deprecated)]
+    let tx_base64 = base64::encode(serialized);
+    Ok(tx_base64)
+}
 
-        let program = provider.program(predix_program::ID)?;
+pub struct PredixSdk {
+    /// Pays transaction fees and rent. Kept hot; never the market authority.
+    fee_payer: Arc<SignerHandle>,
+    /// Market admin authority used for initialize/match/settle instructions.
+    authority: Arc<SignerHandle>,
+    program: Program<Arc<SignerHandle>>,
+    program_id: Pubkey,
+    rpc: Arc<RpcClient>,
+    nonce_pool: Option<NoncePool>,
+    config: PredixConfig,
+}
+
+impl PredixSdk {
+    pub fn new(
+        config: PredixConfig,
+        fee_payer: Arc<dyn TransactionSigner>,
+        authority: Arc<dyn TransactionSigner>,
+    ) -> Result<Self> {
+        let fee_payer = Arc::new(SignerHandle::new(fee_payer));
+        let authority = Arc::new(SignerHandle::new(authority));
+
+        let provider =
+            Client::new_with_options(config.cluster(), fee_payer.clone(), config.commitment);
+
+        let program = provider.program(config.program_id)?;
+        let rpc = Arc::new(config.rpc_client());
+        let nonce_pool =
+            (!config.nonce_accounts.is_empty()).then(|| NoncePool::new(config.nonce_accounts.clone()));
 
         Ok(Self {
-            keypair: keypair_arc,
+            fee_payer,
+            authority,
             program,
+            program_id: config.program_id,
+            rpc,
+            nonce_pool,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &PredixConfig {
+        &self.config
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    pub fn rpc(&self) -> Arc<RpcClient> {
+        self.rpc.clone()
+    }
+
+    pub fn fee_payer(&self) -> Pubkey {
+        self.fee_payer.pubkey()
+    }
+
+    pub fn authority(&self) -> Pubkey {
+        self.authority.pubkey()
+    }
+
+    /// Present when durable-nonce mode is configured.
+    pub fn nonce_pool(&self) -> Option<&NoncePool> {
+        self.nonce_pool.as_ref()
+    }
+
+    /// Adds the fee payer signature to a transaction that other parties
+    /// (usually the user's wallet) still have to sign.
+    pub async fn partial_sign_as_fee_payer(
+        &self,
+        tx: Transaction,
+        recent_blockhash: Hash,
+    ) -> Result<Transaction> {
+        sign_tx(tx, vec![self.fee_payer.clone()], recent_blockhash).await
+    }
+
+    /// The fee payer, plus the authority when it is a different key.
+    fn signers(&self, with_authority: bool) -> Vec<Arc<SignerHandle>> {
+        let mut signers = vec![self.fee_payer.clone()];
+        if with_authority && self.authority.pubkey() != self.fee_payer.pubkey() {
+            signers.push(self.authority.clone());
+        }
+        signers
+    }
+
+    /// Builds a transaction paid by the fee payer and signs it with the fee
+    /// payer and the authority. It is not sent: callers record it first, then
+    /// submit it with [`PredixSdk::send`] and wait for [`PredixSdk::confirm`].
+    async fn sign_as_authority(&self, ixs: &[Instruction]) -> Result<BuiltTx> {
+        self.sign_fully(ixs, true).await
+    }
+
+    async fn sign_fully(&self, ixs: &[Instruction], with_authority: bool) -> Result<BuiltTx> {
+        let (recent_blockhash, last_valid_block_height) = self
+            .rpc
+            .get_latest_blockhash_with_commitment(self.config.commitment)
+            .await?;
+        let message = Message::new(ixs, Some(&self.fee_payer.pubkey()));
+        let tx = Transaction::new_unsigned(message);
+        let tx = sign_tx(tx, self.signers(with_authority), recent_blockhash).await?;
+        Ok(BuiltTx {
+            signature: tx.signatures[0],
+            tx_base64: encode_tx(&tx)?,
+            recent_blockhash,
+            last_valid_block_height,
+            nonce_account: None,
         })
     }
 
+    async fn send_and_confirm(&self, ixs: &[Instruction], with_authority: bool) -> Result<BuiltTx> {
+        let tx = self.sign_fully(ixs, with_authority).await?;
+        self.send(&tx).await?;
+        self.confirm(&tx).await?;
+        Ok(tx)
+    }
+
+    /// Submits a fully signed transaction without waiting for it to land.
+    pub async fn send(&self, tx: &BuiltTx) -> Result<Signature> {
+        self.rebroadcast(&tx.tx_base64).await
+    }
+
+    /// Waits until a sent transaction lands at the configured commitment.
+    ///
+    /// Fails if it lands with an error, or once the chain is past its last
+    /// valid block height, after which it can no longer land.
+    pub async fn confirm(&self, tx: &BuiltTx) -> Result<()> {
+        loop {
+            let status = self
+                .rpc
+                .get_signature_status_with_commitment(&tx.signature, self.config.commitment)
+                .await?;
+            if let Some(status) = status {
+                return status.map_err(|e| anyhow::anyhow!("Transaction failed: {}", e));
+            }
+            if tx.nonce_account.is_none()
+                && self.rpc.get_block_height().await? > tx.last_valid_block_height
+            {
+                return Err(anyhow::anyhow!("Blockhash expired before the transaction landed"));
+            }
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Builds a transaction paid by the fee payer, signed by the fee payer
+    /// (and the authority when `with_authority` is set), for the user to co-sign.
+    ///
+    /// In durable-nonce mode the transaction leases a nonce account and starts
+    /// with an advance-nonce instruction, so it stays valid until the user
+    /// signs it or the lease runs out. When every nonce account is leased it
+    /// falls back to a recent blockhash.
+    pub async fn partial_tx(&self, ixs: &[Instruction], with_authority: bool) -> Result<BuiltTx> {
+        let mut all_ixs = Vec::with_capacity(ixs.len() + 1);
+        let leased = self
+            .nonce_pool
+            .as_ref()
+            .and_then(|pool| Some((pool, pool.acquire()?)));
+        let (recent_blockhash, last_valid_block_height, nonce_account) = match leased {
+            Some((pool, account)) => {
+                let nonce = nonce::fetch_nonce(
+                    &self.rpc,
+                    &account,
+                    &self.fee_payer.pubkey(),
+                    self.config.commitment,
+                )
+                .await;
+                let nonce = match nonce {
+                    std::result::Result::Ok(nonce) => nonce,
+                    Err(e) => {
+                        pool.release(&account);
+                        return Err(e);
+                    }
+                };
+                // the advance-nonce instruction must come first
+                all_ixs.push(system_instruction::advance_nonce_account(
+                    &account,
+                    &self.fee_payer.pubkey(),
+                ));
+                (nonce, 0, Some(account))
+            }
+            None => {
+                let (blockhash, last_valid_block_height) = self
+                    .rpc
+                    .get_latest_blockhash_with_commitment(self.config.commitment)
+                    .await?;
+                (blockhash, last_valid_block_height, None)
+            }
+        };
+        all_ixs.extend_from_slice(ixs);
+        let message = Message::new(&all_ixs, Some(&self.fee_payer.pubkey()));
+        let tx = Transaction::new_unsigned(message);
+        let tx = match sign_tx(tx, self.signers(with_authority), recent_blockhash).await {
+            std::result::Result::Ok(tx) => tx,
+            Err(e) => {
+                if let (Some(pool), Some(account)) = (&self.nonce_pool, nonce_account) {
+                    pool.release(&account);
+                }
+                return Err(e);
+            }
+        };
+
+        Ok(BuiltTx {
+            // the fee payer signs first, so its signature is the transaction id
+            signature: tx.signatures[0],
+            tx_base64: encode_tx(&tx)?,
+            recent_blockhash,
+            last_valid_block_height,
+            nonce_account,
+        })
+    }
+
+    /// Advances a durable nonce, invalidating any unsigned transaction still
+    /// built on its current value, and releases it back to the pool.
+    ///
+    /// Only the fee payer, the nonce authority, signs. If the advance fails
+    /// the account stays leased, as the old value may still be signed, and
+    /// the caller retries later.
+    pub async fn advance_nonce(&self, account: &Pubkey) -> Result<BuiltTx> {
+        let ix = system_instruction::advance_nonce_account(account, &self.fee_payer.pubkey());
+        let result = self.send_and_confirm(&[ix], false).await;
+        if let Some(pool) = &self.nonce_pool {
+            match &result {
+                std::result::Result::Ok(_) => pool.release(account),
+                Err(_) => pool.mark_leased(*account),
+            }
+        }
+        result
+    }
+
+    /// Re-sends an already fully signed transaction, e.g. one that was dropped
+    /// before its blockhash expired.
+    pub async fn rebroadcast(&self, tx_base64: &str) -> Result<Signature> {
+        #[allow(deprecated)]
+        let bytes = base64::decode(tx_base64)?;
+        let tx: Transaction = bincode::deserialize(&bytes)?;
+        let signature = self.rpc.send_transaction(&tx).await?;
+        Ok(signature)
+    }
+
+    /// Reads and decodes the on-chain `Market` account.
+    pub async fn fetch_market(&self, market_id: u64) -> Result<MarketAccount> {
+        let (market_pda, _bump) = derive_market_pda(market_id, &self.program_id);
+        let data = self.rpc.get_account_data(&market_pda).await?;
+        let market = MarketAccount::try_deserialize(&mut data.as_slice())?;
+        Ok(market)
+    }
+
+    /// Builds and signs the market initialization; see [`PredixSdk::send`].
     pub async fn create_market(
         &self,
         market_id: u64,
         collateral_mint: Pubkey,
         metadata_url: String,
         expiration_timestamp: i64,
-    ) -> Result<()> {
-        let (market_pda, _bump) = derive_market_pda(market_id, &predix_program::ID);
-        let (vault_pda, _bump) = vault_pda(market_id, &predix_program::ID);
+    ) -> Result<BuiltTx> {
+        let (market_pda, _bump) = derive_market_pda(market_id, &self.program_id);
+        let (vault_pda, _bump) = vault_pda(market_id, &self.program_id);
         let ((yes_mint_pda, _), (no_mint_pda, _)) =
-            derive_yes_and_no_mint_pdas(market_id, &predix_program::ID);
+            derive_yes_and_no_mint_pdas(market_id, &self.program_id);
         dbg!("Market PDA:", market_pda);
         dbg!("Vault PDA:", vault_pda);
         dbg!("Yes Mint PDA:", yes_mint_pda);
@@ -77,7 +344,7 @@ impl PredixSdk {
             collateral_mint,
             yes_mint: yes_mint_pda,
             no_mint: no_mint_pda,
-            admin: self.keypair.pubkey(),
+            admin: self.authority.pubkey(),
             system_program: system_program::ID,
             token_program: spl_token::ID,
             associated_token_program: spl_associated_token_account::ID,
@@ -90,24 +357,25 @@ impl PredixSdk {
         };
         dbg!("Creating market with ID: {}", market_id);
 
-        let tx = self
+        let ixs = self
             .program
             .request()
             .accounts(accounts)
             .args(args)
-            .send()
-            .await?;
+            .instructions()?;
+        let tx = self.sign_as_authority(&ixs).await?;
 
-        println!("Transaction signature: {}", tx);
-        Ok(())
+        println!("Transaction signature: {}", tx.signature);
+        Ok(tx)
     }
 
+    /// Builds and signs the settlement of matched fills; see [`PredixSdk::send`].
     pub async fn place_order(
         &self,
         market_id: u64,
         match_fills: Vec<MatchFill>,
         remaining_accounts: Vec<AccountMeta>,
-    ) -> Result<()> {
+    ) -> Result<BuiltTx> {
         dbg!("Placing order on market ID: {}", market_id);
         dbg!("Match fills: {:?}", &match_fills);
         dbg!("Remaining accounts: {:?}", &remaining_accounts);
@@ -118,27 +386,27 @@ impl PredixSdk {
         if remaining_accounts.len() < require_accounts {
             return Err(anyhow::anyhow!("Insufficient remaining accounts provided"));
         }
-        let (market_pda, _bump) = derive_market_pda(market_id, &predix_program::ID);
+        let (market_pda, _bump) = derive_market_pda(market_id, &self.program_id);
 
         let args = args::ExecuteMatchMulti { fills: match_fills };
 
         let accounts = accounts::ExecuteMatchMulti {
             market: market_pda,
-            admin: self.keypair.pubkey(),
+            admin: self.authority.pubkey(),
             token_program: spl_token::ID,
         };
 
-        let tx = self
+        let ixs = self
             .program
             .request()
             .accounts(accounts)
             .args(args)
             .accounts(remaining_accounts)
-            .send()
-            .await?;
+            .instructions()?;
+        let tx = self.sign_as_authority(&ixs).await?;
 
-        println!("Transaction signature: {}", tx);
-        Ok(())
+        println!("Transaction signature: {}", tx.signature);
+        Ok(tx)
     }
 
     pub async fn split_order(
@@ -147,12 +415,12 @@ impl PredixSdk {
         user_wallet: &Pubkey,
         collateral_mint: &Pubkey,
         amount: u64,
-    ) -> Result<String> {
+    ) -> Result<BuiltTx> {
         dbg!("Splitting order on market ID: {}", market_id);
-        let (market_pda, _bump) = derive_market_pda(market_id, &predix_program::ID);
-        let (vault_pda, _bump) = vault_pda(market_id, &predix_program::ID);
+        let (market_pda, _bump) = derive_market_pda(market_id, &self.program_id);
+        let (vault_pda, _bump) = vault_pda(market_id, &self.program_id);
         let ((yes_mint_pda, _), (no_mint_pda, _)) =
-            derive_yes_and_no_mint_pdas(market_id, &predix_program::ID);
+            derive_yes_and_no_mint_pdas(market_id, &self.program_id);
         let user_collateral_ata = derive_user_collateral_ata_pda(user_wallet, collateral_mint);
         let (user_yes_ata, user_no_ata) =
             derive_yes_and_no_ata_pdas(user_wallet, &yes_mint_pda, &no_mint_pda);
@@ -172,7 +440,7 @@ impl PredixSdk {
             yes_mint: yes_mint_pda,
             no_mint: no_mint_pda,
             user: *user_wallet,
-            admin: self.keypair.pubkey(),
+            admin: self.authority.pubkey(),
             token_program: spl_token::ID,
             associated_token_program: spl_associated_token_account::ID,
             system_program: system_program::ID,
@@ -180,24 +448,14 @@ impl PredixSdk {
 
         let args = args::SplitToken { market_id, amount };
 
-        let mut ix_vec = self
+        let ix_vec = self
             .program
             .request()
             .accounts(accounts)
             .args(args)
             .instructions()?;
         dbg!("Split order ix: {:?}", &ix_vec);
-        let recent_blockhash = self.program.rpc().get_latest_blockhash().await?;
-
-        let message = Message::new(&[ix_vec.remove(0)], Some(&self.keypair.pubkey()));
-        let mut tx = Transaction::new_unsigned(message);
-        tx.try_partial_sign(&[self.keypair.as_ref()], recent_blockhash)?;
-
-        let serialized = bincode::serialize(&tx)?;
-        #[allow(deprecated)]
-        let tx_base64 = base64::encode(serialized);
-
-        Ok(tx_base64)
+        self.partial_tx(&ix_vec, true).await
     }
 
     pub async fn merge_order(
@@ -206,11 +464,11 @@ impl PredixSdk {
         user_wallet: &Pubkey,
         collateral_mint: &Pubkey,
         amount: u64,
-    ) -> Result<String> {
-        let (market_pda, _bump) = derive_market_pda(market_id, &predix_program::ID);
-        let (vault_pda, _bump) = vault_pda(market_id, &predix_program::ID);
+    ) -> Result<BuiltTx> {
+        let (market_pda, _bump) = derive_market_pda(market_id, &self.program_id);
+        let (vault_pda, _bump) = vault_pda(market_id, &self.program_id);
         let ((yes_mint_pda, _), (no_mint_pda, _)) =
-            derive_yes_and_no_mint_pdas(market_id, &predix_program::ID);
+            derive_yes_and_no_mint_pdas(market_id, &self.program_id);
         let user_collateral_ata = derive_user_collateral_ata_pda(user_wallet, collateral_mint);
         let (user_yes_ata, user_no_ata) =
             derive_yes_and_no_ata_pdas(user_wallet, &yes_mint_pda, &no_mint_pda);
@@ -237,7 +495,7 @@ impl PredixSdk {
             system_program: system_program::ID,
         };
 
-        let mut ix_vec = self
+        let ix_vec = self
             .program
             .request()
             .accounts(accounts)
@@ -245,46 +503,28 @@ impl PredixSdk {
             .instructions()?;
 
         dbg!("Merge order ix: {:?}", &ix_vec);
-        let recent_blockhash = self.program.rpc().get_latest_blockhash().await?;
-        let message = Message::new(&[ix_vec.remove(0)], Some(&self.keypair.pubkey()));
-        let mut tx = Transaction::new_unsigned(message);
-        tx.try_partial_sign(&[self.keypair.as_ref()], recent_blockhash)?;
-
-        let serialized = bincode::serialize(&tx)?;
-        #[allow(deprecated)]
-        let tx_base64 = base64::encode(serialized);
-
-        Ok(tx_base64)
+        self.partial_tx(&ix_vec, false).await
     }
 
-    pub async fn set_winner(&self, market_id: u64, outcome: MarketOutcome) -> Result<String> {
-        let (market_pda, _bump) = derive_market_pda(market_id, &predix_program::ID);
+    pub async fn set_winner(&self, market_id: u64, outcome: MarketOutcome) -> Result<BuiltTx> {
+        let (market_pda, _bump) = derive_market_pda(market_id, &self.program_id);
         dbg!("Market PDA:", market_pda);
 
         let accounts = accounts::SetWinner {
             market: market_pda,
-            admin: self.keypair.pubkey(),
+            admin: self.authority.pubkey(),
         };
         let args = args::SetWinner {
             is_settled: true,
             outcome,
         };
-        let mut ix_vec = self
+        let ix_vec = self
             .program
             .request()
             .accounts(accounts)
             .args(args)
             .instructions()?;
-        
-        let recent_blockhash = self.program.rpc().get_latest_blockhash().await?;
-        let message = Message::new(&[ix_vec.remove(0)], Some(&self.keypair.pubkey()));
-        let mut tx = Transaction::new_unsigned(message);
-        tx.try_partial_sign(&[self.keypair.as_ref()], recent_blockhash)?;
-
-        let serialized = bincode::serialize(&tx)?;
-        #[allow(deprecated)]
-        let tx_base64 = base64::encode(serialized);
 
-        Ok(tx_base64)
+        self.partial_tx(&ix_vec, true).await
     }
 }
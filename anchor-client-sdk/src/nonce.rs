@@ -0,0 +1,79 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use anchor_client::solana_sdk::{hash::Hash, pubkey::Pubkey};
+use anyhow::{Result, anyhow};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    nonce_utils::nonblocking::{data_from_account, get_account_with_commitment},
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+
+/// Durable nonce accounts available for user co-signed transactions.
+///
+/// A nonce value can back only one transaction, so each account is leased to
+/// a single outstanding transaction and released once that transaction lands
+/// or is abandoned (and its nonce advanced).
+#[derive(Debug)]
+pub struct NoncePool {
+    accounts: Vec<Pubkey>,
+    leased: Mutex<HashSet<Pubkey>>,
+}
+
+impl NoncePool {
+    pub fn new(accounts: Vec<Pubkey>) -> Self {
+        Self {
+            accounts,
+            leased: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn contains(&self, account: &Pubkey) -> bool {
+        self.accounts.contains(account)
+    }
+
+    /// Leases a free nonce account, or returns `None` when all of them are
+    /// leased.
+    pub fn acquire(&self) -> Option<Pubkey> {
+        let mut leased = self.leased.lock().ok()?;
+        let account = self
+            .accounts
+            .iter()
+            .find(|a| !leased.contains(*a))
+            .copied()?;
+        leased.insert(account);
+        Some(account)
+    }
+
+    /// Marks an account as leased, e.g. when restoring outstanding
+    /// transactions after a restart.
+    pub fn mark_leased(&self, account: Pubkey) {
+        if let Ok(mut leased) = self.leased.lock() {
+            leased.insert(account);
+        }
+    }
+
+    pub fn release(&self, account: &Pubkey) {
+        if let Ok(mut leased) = self.leased.lock() {
+            leased.remove(account);
+        }
+    }
+}
+
+/// Reads the nonce value currently stored in a nonce account, checking that
+/// `authority` may advance it.
+pub async fn fetch_nonce(
+    rpc: &RpcClient,
+    account: &Pubkey,
+    authority: &Pubkey,
+    commitment: CommitmentConfig,
+) -> Result<Hash> {
+    let account_data = get_account_with_commitment(rpc, account, commitment).await?;
+    let data = data_from_account(&account_data)?;
+    if &data.authority != authority {
+        return Err(anyhow!(
+            "Nonce account {} is not controlled by the fee payer",
+            account
+        ));
+    }
+    Ok(data.blockhash())
+}
@@ -0,0 +1,332 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+#[cfg(any(test, feature = "test-signer"))]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, read_keypair_file},
+    signer::{Signer, SignerError},
+};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+/// Anything that can produce ed25519 signatures for a single pubkey.
+///
+/// The SDK never holds raw key material itself; it only talks to a
+/// `TransactionSigner`, so the key can live in a file, behind a remote
+/// signing service, or in memory for tests.
+pub trait TransactionSigner: Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+    fn sign_message(&self, message: &[u8]) -> Result<Signature>;
+}
+
+/// Adapter that lets a `TransactionSigner` be used wherever the solana
+/// `Signer` trait is expected (`Transaction::try_sign`, anchor `Program`).
+#[derive(Clone)]
+pub struct SignerHandle(Arc<dyn TransactionSigner>);
+
+impl SignerHandle {
+    pub fn new(inner: Arc<dyn TransactionSigner>) -> Self {
+        Self(inner)
+    }
+}
+
+impl Signer for SignerHandle {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.0.pubkey())
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.0
+            .sign_message(message)
+            .map_err(|e| SignerError::Custom(e.to_string()))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// Signer backed by a solana-cli style JSON keypair file.
+pub struct FileSigner {
+    keypair: Keypair,
+}
+
+impl FileSigner {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let keypair = read_keypair_file(path.as_ref()).map_err(|e| {
+            anyhow!(
+                "Failed to read keypair file {}: {}",
+                path.as_ref().display(),
+                e
+            )
+        })?;
+        Ok(Self { keypair })
+    }
+}
+
+impl TransactionSigner for FileSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        Ok(self.keypair.try_sign_message(message)?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RemoteTransport {
+    /// Plain HTTP endpoint, e.g. `http://127.0.0.1:9000/sign`.
+    Http { host: String, path: String },
+    /// Unix domain socket speaking newline-delimited JSON.
+    Unix(PathBuf),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum RemoteRequest {
+    GetPubkey,
+    SignMessage { pubkey: String, message: String },
+}
+
+#[derive(Deserialize)]
+struct RemoteResponse {
+    pubkey: Option<String>,
+    signature: Option<String>,
+    error: Option<String>,
+}
+
+/// Signer that forwards every signing request to an external signing service.
+///
+/// Protocol: each request is a JSON object with a `method` of `get_pubkey` or
+/// `sign_message` (base64 `message`), and the service answers with a JSON
+/// object carrying `pubkey`, `signature` (base58) or `error`. Over HTTP the
+/// object is the POST body; over a Unix socket it is a single line.
+pub struct RemoteSigner {
+    transport: RemoteTransport,
+    pubkey: Pubkey,
+    timeout: Duration,
+}
+
+impl RemoteSigner {
+    pub fn connect(transport: RemoteTransport, timeout: Duration) -> Result<Self> {
+        let resp = call_remote(&transport, timeout, &RemoteRequest::GetPubkey)?;
+        let pubkey = resp
+            .pubkey
+            .ok_or_else(|| anyhow!("Remote signer did not return a pubkey"))?;
+        let pubkey = Pubkey::from_str(&pubkey)
+            .map_err(|e| anyhow!("Remote signer returned an invalid pubkey: {}", e))?;
+        Ok(Self {
+            transport,
+            pubkey,
+            timeout,
+        })
+    }
+}
+
+impl TransactionSigner for RemoteSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        #[allow(deprecated)]
+        let message = base64::encode(message);
+        let req = RemoteRequest::SignMessage {
+            pubkey: self.pubkey.to_string(),
+            message,
+        };
+        let resp = call_remote(&self.transport, self.timeout, &req)?;
+        let signature = resp
+            .signature
+            .ok_or_else(|| anyhow!("Remote signer did not return a signature"))?;
+        Signature::from_str(&signature)
+            .map_err(|e| anyhow!("Remote signer returned an invalid signature: {}", e))
+    }
+}
+
+fn call_remote(
+    transport: &RemoteTransport,
+    timeout: Duration,
+    req: &RemoteRequest,
+) -> Result<RemoteResponse> {
+    let body = serde_json::to_string(req)?;
+    let raw = match transport {
+        RemoteTransport::Http { host, path } => {
+            let addr = host
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| anyhow!("Remote signer host {} did not resolve", host))?;
+            let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+            stream.set_read_timeout(Some(timeout))?;
+            stream.set_write_timeout(Some(timeout))?;
+            let request = format!(
+                "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                path,
+                host,
+                body.len(),
+                body
+            );
+            stream.write_all(request.as_bytes())?;
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response)?;
+            http_body(&response)?
+        }
+        RemoteTransport::Unix(path) => {
+            let mut stream = UnixStream::connect(path)?;
+            stream.set_read_timeout(Some(timeout))?;
+            stream.set_write_timeout(Some(timeout))?;
+            stream.write_all(body.as_bytes())?;
+            stream.write_all(b"\n")?;
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line)?;
+            line
+        }
+    };
+    let resp: RemoteResponse = serde_json::from_str(raw.trim())?;
+    if let Some(err) = resp.error {
+        bail!("Remote signer error: {}", err);
+    }
+    Ok(resp)
+}
+
+/// Extracts the body of a `Connection: close` HTTP/1.1 response, undoing
+/// chunked transfer encoding when the server used it.
+fn http_body(response: &[u8]) -> Result<String> {
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| anyhow!("Malformed HTTP response from remote signer"))?;
+    let head = std::str::from_utf8(&response[..split])?;
+    let payload = &response[split + 4..];
+    let mut lines = head.lines();
+    let status_line = lines.next().unwrap_or_default();
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        bail!("Remote signer returned {}", status_line);
+    }
+    let chunked = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.to_ascii_lowercase().contains("chunked")
+        })
+    });
+    let body = if chunked {
+        decode_chunked(payload)?
+    } else {
+        payload.to_vec()
+    };
+    Ok(String::from_utf8(body)?)
+}
+
+fn decode_chunked(mut payload: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = payload
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(|| anyhow!("Truncated chunked response from remote signer"))?;
+        let size_line = std::str::from_utf8(&payload[..line_end])?;
+        // chunk extensions follow a ';' and carry nothing we need
+        let size_hex = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| anyhow!("Invalid chunk size {:?} from remote signer", size_hex))?;
+        payload = &payload[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if payload.len() < size + 2 {
+            bail!("Truncated chunked response from remote signer");
+        }
+        body.extend_from_slice(&payload[..size]);
+        payload = &payload[size + 2..];
+    }
+}
+
+/// In-memory signer for tests. Counts signatures and can be switched to fail.
+#[cfg(any(test, feature = "test-signer"))]
+pub struct TestSigner {
+    keypair: Keypair,
+    signed: AtomicUsize,
+    fail: AtomicBool,
+}
+
+#[cfg(any(test, feature = "test-signer"))]
+impl TestSigner {
+    pub fn new() -> Self {
+        Self {
+            keypair: Keypair::new(),
+            signed: AtomicUsize::new(0),
+            fail: AtomicBool::new(false),
+        }
+    }
+
+    pub fn signed_count(&self) -> usize {
+        self.signed.load(Ordering::SeqCst)
+    }
+
+    pub fn set_fail(&self, fail: bool) {
+        self.fail.store(fail, Ordering::SeqCst);
+    }
+}
+
+#[cfg(any(test, feature = "test-signer"))]
+impl Default for TestSigner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "test-signer"))]
+impl TransactionSigner for TestSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        if self.fail.load(Ordering::SeqCst) {
+            bail!("Test signer configured to fail");
+        }
+        self.signed.fetch_add(1, Ordering::SeqCst);
+        Ok(self.keypair.try_sign_message(message)?)
+    }
+}
+
+/// Builds a signer from a URI:
+/// - `file:/path/to/keypair.json`
+/// - `http://host:port/path`
+/// - `unix:/path/to/signer.sock`
+/// - `test:` (ephemeral in-memory key; only with the `test-signer` feature)
+pub fn signer_from_uri(uri: &str, timeout: Duration) -> Result<Arc<dyn TransactionSigner>> {
+    if let Some(path) = uri.strip_prefix("file:") {
+        return Ok(Arc::new(FileSigner::from_file(path)?));
+    }
+    if let Some(rest) = uri.strip_prefix("http://") {
+        let (host, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+        let transport = RemoteTransport::Http {
+            host: host.to_string(),
+            path: path.to_string(),
+        };
+        return Ok(Arc::new(RemoteSigner::connect(transport, timeout)?));
+    }
+    if let Some(path) = uri.strip_prefix("unix:") {
+        let transport = RemoteTransport::Unix(PathBuf::from(path));
+        return Ok(Arc::new(RemoteSigner::connect(transport, timeout)?));
+    }
+    #[cfg(any(test, feature = "test-signer"))]
+    if uri == "test:" {
+        return Ok(Arc::new(TestSigner::new()));
+    }
+    bail!("Unsupported signer uri: {}", uri)
+}
@@ -1,7 +1,8 @@
-use anchor_lang::declare_program;
 use anchor_lang::prelude::AccountMeta;
 use anchor_lang::prelude::Pubkey;
+use solana_sdk::program_pack::Pack;
 use matching::types::Trade;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::*;
 
@@ -9,8 +10,6 @@ use rust_decimal::prelude::*;
 use crate::predix_program::types::TradeSide;
 use crate::predix_program::types::{MatchFill};
 
-declare_program!(predix_program);
-
 
 pub fn derive_market_pda(market_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
     let market_id_bytes = market_id.to_le_bytes();
@@ -62,33 +61,112 @@ pub fn derive_no_ata(
     spl_associated_token_account::get_associated_token_address(user_wallet, no_mint)
 }   
 
-fn to_u64_amount(dec: Decimal) -> u64{
-    (dec * Decimal::new(1_000_000, 0)).to_u64().unwrap()
+/// Reads the decimals of an SPL mint from chain.
+pub async fn fetch_mint_decimals(rpc: &RpcClient, mint: &Pubkey) -> anyhow::Result<u8> {
+    let data = rpc.get_account_data(mint).await?;
+    let mint = spl_token::state::Mint::unpack(&data)?;
+    Ok(mint.decimals)
+}
+
+/// Converts a UI amount into base units of a mint with `decimals` decimals.
+///
+/// Fails on negative amounts and on anything that does not fit in a `u64`
+/// instead of panicking.
+pub fn to_base_units(
+    amount: Decimal,
+    decimals: u8,
+    rounding: RoundingStrategy,
+) -> anyhow::Result<u64> {
+    if amount < Decimal::ZERO {
+        anyhow::bail!("Amount {} is negative", amount);
+    }
+    let scale = 10u64
+        .checked_pow(decimals as u32)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported mint decimals {}", decimals))?;
+    amount
+        .checked_mul(Decimal::from(scale))
+        .map(|v| v.round_dp_with_strategy(0, rounding))
+        .and_then(|v| v.to_u64())
+        .ok_or_else(|| anyhow::anyhow!("Amount {} overflows u64 at {} decimals", amount, decimals))
+}
+
+/// True when `amount` has no more fractional digits than the mint supports.
+pub fn is_representable(amount: Decimal, decimals: u8) -> bool {
+    amount.normalize().scale() <= decimals as u32
 }
 
-pub fn get_match_fills(trade: &Vec<Trade>, side: TradeSide) -> Vec<MatchFill> {
+/// Checks an incoming limit order against the market's mints: price and size
+/// must be positive, exactly representable, and the worst-case charge
+/// (`price * qty`, rounded up) must fit in a `u64`.
+pub fn validate_order_amounts(
+    price: Decimal,
+    qty: Decimal,
+    share_decimals: u8,
+    collateral_decimals: u8,
+) -> anyhow::Result<()> {
+    if price <= Decimal::ZERO || qty <= Decimal::ZERO {
+        anyhow::bail!("Price and quantity must be positive");
+    }
+    if !is_representable(price, collateral_decimals) {
+        anyhow::bail!(
+            "Price {} has more than {} decimal places",
+            price,
+            collateral_decimals
+        );
+    }
+    if !is_representable(qty, share_decimals) {
+        anyhow::bail!(
+            "Quantity {} has more than {} decimal places",
+            qty,
+            share_decimals
+        );
+    }
+    let notional = price
+        .checked_mul(qty)
+        .ok_or_else(|| anyhow::anyhow!("Order notional overflows"))?;
+    to_base_units(notional, collateral_decimals, RoundingStrategy::AwayFromZero)?;
+    Ok(())
+}
+
+/// Builds the on-chain fills for a set of trades.
+///
+/// Shares and price are both rounded toward zero, so the buyer is never
+/// charged more than `quantity * price` and never receives more shares than
+/// were matched. Orders are validated with [`validate_order_amounts`] on
+/// entry, so in practice no rounding happens here.
+pub fn get_match_fills(
+    trade: &Vec<Trade>,
+    side: TradeSide,
+    share_decimals: u8,
+    collateral_decimals: u8,
+) -> anyhow::Result<Vec<MatchFill>> {
     let mut match_fills: Vec<MatchFill> = Vec::new();
     for t in trade.iter() {
         match_fills.push(MatchFill {
-            shares: to_u64_amount(t.quantity),
-            price: to_u64_amount(t.price),
+            shares: to_base_units(t.quantity, share_decimals, RoundingStrategy::ToZero)?,
+            price: to_base_units(t.price, collateral_decimals, RoundingStrategy::ToZero)?,
             side,
         });
     }
-    match_fills
+    Ok(match_fills)
 }
 
-pub fn get_remaining_accounts(trade: &Vec<Trade>, side: TradeSide, market_id: u64) -> Vec<AccountMeta> {
+pub fn get_remaining_accounts(
+    trade: &Vec<Trade>,
+    side: TradeSide,
+    market_id: u64,
+    program_id: &Pubkey,
+    collateral_mint: &Pubkey,
+) -> Vec<AccountMeta> {
     let mut remaining_accounts: Vec<AccountMeta> = Vec::new();
-    let market_pda = derive_market_pda(market_id, &predix_program::ID);
+    let market_pda = derive_market_pda(market_id, program_id);
     dbg!("Market PDA in remaining accounts:", market_pda.0);
     for t in trade.iter() {
-        let (yes_mint_pda, no_mint_pda) = derive_yes_and_no_mint_pdas(market_id, &predix_program::ID);
+        let (yes_mint_pda, no_mint_pda) = derive_yes_and_no_mint_pdas(market_id, program_id);
         let buyer_pubkey = t.buyer_address.parse::<Pubkey>().unwrap();
         let seller_pubkey = t.seller_address.parse::<Pubkey>().unwrap();
-        let collateral_mint = Pubkey::from_str("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU").unwrap();
-        let buyer_collateral = derive_user_collateral_ata_pda(&buyer_pubkey, &collateral_mint);
-        let seller_collateral = derive_user_collateral_ata_pda(&seller_pubkey, &collateral_mint);
+        let buyer_collateral = derive_user_collateral_ata_pda(&buyer_pubkey, collateral_mint);
+        let seller_collateral = derive_user_collateral_ata_pda(&seller_pubkey, collateral_mint);
         let buyer_ata;
         let seller_ata;
         match side {
@@ -40,6 +40,7 @@ pub fn build_app(state: Arc<AppState>) -> Router {
         .nest("/markets", routes::markets::router())
         .nest("/orders", routes::orders::router())
         .nest("/orderbook", routes::orderbook::router())
+        .nest("/portfolio", routes::portfolio::router())
         .layer(cors)
         .with_state(state);
 
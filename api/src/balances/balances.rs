@@ -0,0 +1,370 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anchor_lang::prelude::Pubkey;
+use axum::http::StatusCode;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::program_pack::Pack;
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::Account;
+use db::queries::account_event::ACCOUNT_EVENTS_CHANNEL;
+use sqlx::postgres::PgListener;
+use uuid::Uuid;
+
+use crate::state::state::Shared;
+
+const BALANCE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BalanceKey {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[derive(Debug)]
+struct CachedBalance {
+    on_chain: u64,
+    reserved: u64,
+    fetched_at: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Reservation {
+    key: BalanceKey,
+    amount: u64,
+    qty: Decimal,
+}
+
+/// Token balances per (owner, mint) plus the part of them locked by resting orders.
+#[derive(Debug, Default)]
+pub struct BalanceCache {
+    balances: HashMap<BalanceKey, CachedBalance>,
+    reservations: HashMap<Uuid, Reservation>,
+}
+
+impl BalanceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn is_fresh(&self, key: &BalanceKey) -> bool {
+        self.balances
+            .get(key)
+            .and_then(|b| b.fetched_at)
+            .is_some_and(|t| t.elapsed() < BALANCE_TTL)
+    }
+
+    fn set_on_chain(&mut self, key: BalanceKey, amount: u64) {
+        let entry = self.balances.entry(key).or_insert(CachedBalance {
+            on_chain: 0,
+            reserved: 0,
+            fetched_at: None,
+        });
+        entry.on_chain = amount;
+        entry.fetched_at = Some(Instant::now());
+    }
+
+    /// Balance not yet locked by other resting orders.
+    pub fn available(&self, key: &BalanceKey) -> u64 {
+        self.balances
+            .get(key)
+            .map(|b| b.on_chain.saturating_sub(b.reserved))
+            .unwrap_or(0)
+    }
+
+    /// Locks `amount` for `order_id`, which rests with `qty` shares.
+    pub fn reserve(
+        &mut self,
+        key: BalanceKey,
+        order_id: Uuid,
+        amount: u64,
+        qty: Decimal,
+    ) -> Result<(), String> {
+        let available = self.available(&key);
+        if amount > available {
+            return Err(format!(
+                "order needs {} but only {} is available",
+                amount, available
+            ));
+        }
+        if let Some(balance) = self.balances.get_mut(&key) {
+            balance.reserved += amount;
+        }
+        self.reservations
+            .insert(order_id, Reservation { key, amount, qty });
+        Ok(())
+    }
+
+    /// Replaces the reservation of a resting order with `amount` for `qty`
+    /// shares. Returns the previous amount and quantity.
+    pub fn resize(
+        &mut self,
+        order_id: Uuid,
+        amount: u64,
+        qty: Decimal,
+    ) -> Result<(u64, Decimal), String> {
+        let Some(reservation) = self.reservations.get_mut(&order_id) else {
+            return Err("order has no reservation".into());
+        };
+        let balance = self.balances.get_mut(&reservation.key);
+        let available = balance
+            .as_ref()
+            .map(|b| b.on_chain.saturating_sub(b.reserved))
+            .unwrap_or(0)
+            + reservation.amount;
+        if amount > available {
+            return Err(format!(
+                "order needs {} but only {} is available",
+                amount, available
+            ));
+        }
+        if let Some(balance) = balance {
+            balance.reserved = balance.reserved.saturating_sub(reservation.amount) + amount;
+        }
+        let previous = (reservation.amount, reservation.qty);
+        reservation.amount = amount;
+        reservation.qty = qty;
+        Ok(previous)
+    }
+
+    /// Releases the share of a reservation that belongs to `filled` shares.
+    /// The balance itself is marked stale since the fill moves tokens on chain.
+    pub fn release_filled(&mut self, order_id: Uuid, filled: Decimal) {
+        let Some(reservation) = self.reservations.get_mut(&order_id) else {
+            return;
+        };
+        let released = if filled >= reservation.qty || reservation.qty.is_zero() {
+            reservation.amount
+        } else {
+            let part = Decimal::from(reservation.amount) * filled / reservation.qty;
+            part.floor().to_u64().unwrap_or(reservation.amount)
+        };
+        reservation.amount -= released;
+        reservation.qty -= filled.min(reservation.qty);
+        let key = reservation.key;
+        if reservation.qty.is_zero() {
+            self.reservations.remove(&order_id);
+        }
+        if let Some(balance) = self.balances.get_mut(&key) {
+            balance.reserved = balance.reserved.saturating_sub(released);
+            balance.fetched_at = None;
+        }
+    }
+
+    /// Releases whatever is still reserved for `order_id` (cancel, expiry, removal).
+    pub fn release(&mut self, order_id: Uuid) {
+        if let Some(reservation) = self.reservations.remove(&order_id) {
+            if let Some(balance) = self.balances.get_mut(&reservation.key) {
+                balance.reserved = balance.reserved.saturating_sub(reservation.amount);
+            }
+        }
+    }
+
+    /// Amounts of `owner`'s balances locked by resting orders, per mint.
+    pub fn reserved_for_owner(&self, owner: &Pubkey) -> Vec<(Pubkey, u64)> {
+        let mut reserved = HashMap::new();
+        for reservation in self.reservations.values() {
+            if &reservation.key.owner == owner {
+                *reserved.entry(reservation.key.mint).or_insert(0u64) += reservation.amount;
+            }
+        }
+        reserved.into_iter().collect()
+    }
+
+    /// Forces the next reservation for `owner` to re-read balances from chain.
+    pub fn invalidate_owner(&mut self, owner: &Pubkey) {
+        for (key, balance) in self.balances.iter_mut() {
+            if &key.owner == owner {
+                balance.fetched_at = None;
+            }
+        }
+    }
+}
+
+async fn fetch_token_balance(rpc: &RpcClient, key: &BalanceKey) -> Result<u64, anyhow::Error> {
+    let ata = get_associated_token_address(&key.owner, &key.mint);
+    let account = rpc
+        .get_multiple_accounts(&[ata])
+        .await?
+        .pop()
+        .flatten();
+    match account {
+        Some(account) => Ok(Account::unpack(&account.data)?.amount),
+        None => Ok(0),
+    }
+}
+
+/// Reserves `amount` of `key` for a new order, refreshing the cached balance
+/// from chain first when it is stale.
+pub async fn reserve_for_order(
+    state: &Shared,
+    key: BalanceKey,
+    order_id: Uuid,
+    amount: u64,
+    qty: Decimal,
+) -> Result<(), (StatusCode, String)> {
+    let fresh = state.balances.lock().await.is_fresh(&key);
+    if !fresh {
+        let on_chain = fetch_token_balance(&state.rpc_client, &key)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch balance: {}", e),
+                )
+            })?;
+        state.balances.lock().await.set_on_chain(key, on_chain);
+    }
+    state
+        .balances
+        .lock()
+        .await
+        .reserve(key, order_id, amount, qty)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Insufficient balance: {}", e)))
+}
+
+/// Like [`reserve_for_order`], for an order that already holds a reservation
+/// under `key`. Returns the previous amount and quantity.
+pub async fn resize_for_order(
+    state: &Shared,
+    key: BalanceKey,
+    order_id: Uuid,
+    amount: u64,
+    qty: Decimal,
+) -> Result<(u64, Decimal), (StatusCode, String)> {
+    let fresh = state.balances.lock().await.is_fresh(&key);
+    if !fresh {
+        let on_chain = fetch_token_balance(&state.rpc_client, &key)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch balance: {}", e),
+                )
+            })?;
+        state.balances.lock().await.set_on_chain(key, on_chain);
+    }
+    state
+        .balances
+        .lock()
+        .await
+        .resize(order_id, amount, qty)
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Insufficient balance: {}", e),
+            )
+        })
+}
+
+/// Listens for balance-changing on-chain events published by the event
+/// listener and invalidates the affected wallets.
+pub async fn run_balance_listener(state: Shared) {
+    loop {
+        if let Err(e) = listen_account_events(&state).await {
+            println!("Balance listener error: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn listen_account_events(state: &Shared) -> Result<(), anyhow::Error> {
+    let mut listener = PgListener::connect_with(&state.db_pool).await?;
+    listener.listen(ACCOUNT_EVENTS_CHANNEL).await?;
+    loop {
+        let notification = listener.recv().await?;
+        match notification.payload().parse::<Pubkey>() {
+            Ok(owner) => state.balances.lock().await.invalidate_owner(&owner),
+            Err(e) => println!("Invalid account event payload: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    /// A cache holding `on_chain` of one freshly read balance.
+    fn cache_with(on_chain: u64) -> (BalanceCache, BalanceKey) {
+        let key = BalanceKey {
+            owner: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+        };
+        let mut cache = BalanceCache::new();
+        cache.set_on_chain(key, on_chain);
+        (cache, key)
+    }
+
+    #[test]
+    fn reserve_locks_balance_until_released() {
+        let (mut cache, key) = cache_with(1_000);
+        let first = Uuid::new_v4();
+        cache.reserve(key, first, 600, dec("10")).unwrap();
+        assert_eq!(cache.available(&key), 400);
+        assert!(cache.reserve(key, Uuid::new_v4(), 500, dec("5")).is_err());
+        assert_eq!(cache.reserved_for_owner(&key.owner), vec![(key.mint, 600)]);
+
+        cache.release(first);
+        assert_eq!(cache.available(&key), 1_000);
+        assert!(cache.reserved_for_owner(&key.owner).is_empty());
+    }
+
+    #[test]
+    fn reserve_refuses_an_unknown_balance() {
+        let (mut cache, key) = cache_with(0);
+        let other = BalanceKey {
+            mint: Pubkey::new_unique(),
+            ..key
+        };
+        assert!(cache.reserve(other, Uuid::new_v4(), 1, dec("1")).is_err());
+    }
+
+    #[test]
+    fn release_filled_frees_the_filled_share_and_marks_the_balance_stale() {
+        let (mut cache, key) = cache_with(1_000);
+        let order_id = Uuid::new_v4();
+        cache.reserve(key, order_id, 600, dec("10")).unwrap();
+
+        cache.release_filled(order_id, dec("4"));
+        assert_eq!(cache.available(&key), 640);
+        assert!(!cache.is_fresh(&key));
+
+        // the last fill releases whatever rounding left behind
+        cache.release_filled(order_id, dec("6"));
+        assert_eq!(cache.available(&key), 1_000);
+        assert!(cache.reserved_for_owner(&key.owner).is_empty());
+    }
+
+    #[test]
+    fn resize_swaps_the_reservation_and_returns_the_previous_one() {
+        let (mut cache, key) = cache_with(1_000);
+        let order_id = Uuid::new_v4();
+        cache.reserve(key, order_id, 600, dec("10")).unwrap();
+
+        // growing may use the order's own reservation plus what is free
+        assert_eq!(
+            cache.resize(order_id, 900, dec("15")),
+            Ok((600, dec("10")))
+        );
+        assert_eq!(cache.available(&key), 100);
+
+        assert!(cache.resize(order_id, 1_100, dec("18")).is_err());
+        assert_eq!(cache.available(&key), 100);
+
+        assert_eq!(cache.resize(order_id, 300, dec("5")), Ok((900, dec("15"))));
+        assert_eq!(cache.available(&key), 700);
+    }
+
+    #[test]
+    fn resize_needs_an_existing_reservation() {
+        let (mut cache, _) = cache_with(1_000);
+        assert!(cache.resize(Uuid::new_v4(), 100, dec("1")).is_err());
+    }
+}
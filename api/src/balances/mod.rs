@@ -0,0 +1 @@
+pub mod balances;
\ No newline at end of file
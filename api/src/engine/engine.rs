@@ -1,27 +1,124 @@
 
 
-use matching::{orderbook::market::MarketBooks, types::{OpenOrder, OrderEntry, Side, SnapshotData, Trade}};
+use std::{collections::HashSet, sync::Arc};
+
+use matching::{orderbook::market::MarketBooks, types::{Amended, BestPrices, CancelFilter, MarketOrder, MarketSnapshot, OpenOrder, OrderEntry, Quote, Side, SnapshotData, Trade}};
 use rust_decimal::Decimal;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{RwLock, mpsc, oneshot};
 use uuid::Uuid;
 
-use crate::models::orders::ShareType;
+use crate::{engine::index::OpenOrderIndex, models::orders::ShareType};
+
+/// Outcome of matching an order: (order id, trades, remaining quantity), or
+/// the reason the engine refused it.
+pub type MatchResult = Result<(Uuid, Vec<Trade>, Decimal), String>;
+
+/// The price a limit order was placed at, which a sliding post-only order may
+/// have moved off the requested one. The requested price if nothing rests.
+fn resting_price(
+    book: &MarketBooks,
+    share: ShareType,
+    order_id: Uuid,
+    requested: Decimal,
+) -> Decimal {
+    let order_book = match share {
+        ShareType::Yes => &book.yes,
+        ShareType::No => &book.no,
+    };
+    order_book.find_order(order_id).map_or(requested, |o| o.price)
+}
+
+/// One operation of a batch. A batch is applied in order within a single
+/// engine step.
+pub enum BatchOp {
+    Place {
+        side: Side,
+        share: ShareType,
+        order: OrderEntry,
+    },
+    /// Cancels a resting order of `user_address` on either outcome.
+    Cancel {
+        order_id: Uuid,
+        user_address: String,
+    },
+}
+
+pub enum BatchOpResult {
+    /// The match result and the order's resting price.
+    Placed(MatchResult, Decimal),
+    /// The removed order, or why nothing was cancelled.
+    Cancelled(Result<OrderEntry, String>),
+}
+
+/// What was left on a market's books when its engine closed.
+pub struct ClosedBook {
+    /// The book as it stood right before the resting orders were cancelled.
+    pub snapshot: MarketSnapshot,
+    pub yes: Vec<OrderEntry>,
+    pub no: Vec<OrderEntry>,
+}
 
+impl ClosedBook {
+    pub fn order_ids(&self) -> Vec<Uuid> {
+        self.yes.iter().chain(self.no.iter()).map(|o| o.id).collect()
+    }
+}
 
 pub enum EngineMsg {
+    /// Answers with the match result and the order's resting price.
     PlaceOrder {
         side: Side,
         share: ShareType,
         trades: OrderEntry,
-        resp: oneshot::Sender<(Uuid, Vec<Trade>, Decimal)>,
+        resp: oneshot::Sender<(MatchResult, Decimal)>,
+    },
+    /// Sweeps the book without resting; `order.price` is the worst price.
+    PlaceMarketOrder {
+        side: Side,
+        share: ShareType,
+        order: OrderEntry,
+        market: MarketOrder,
+        resp: oneshot::Sender<MatchResult>,
     },
     CloseOrder {
         side: Side,
         share: ShareType,
         price: Decimal,
         order_id: Uuid,
+        user_address: String,
         resp: oneshot::Sender<(bool, String)>,
     },
+    /// Changes the size and/or price of a resting order of `user_address` in
+    /// one step; see `OrderBook::amend_order`.
+    Amend {
+        share: ShareType,
+        order_id: Uuid,
+        user_address: String,
+        qty: Option<Decimal>,
+        price: Option<Decimal>,
+        resp: oneshot::Sender<Result<Amended, String>>,
+    },
+    /// Removes every resting order matching `filter`, returning them.
+    CancelAll {
+        filter: CancelFilter,
+        resp: oneshot::Sender<Vec<OrderEntry>>,
+    },
+    /// Applies `ops` in order without handling any other message in between.
+    Batch {
+        ops: Vec<BatchOp>,
+        resp: oneshot::Sender<Vec<BatchOpResult>>,
+    },
+    /// Drops makers that failed pre-trade checks and re-matches the taker
+    /// quantity that had been filled against them. A taker with `market` set
+    /// is re-swept as a market order.
+    RejectMakers {
+        side: Side,
+        share: ShareType,
+        taker: OrderEntry,
+        market: Option<MarketOrder>,
+        maker_order_ids: Vec<Uuid>,
+        resp: oneshot::Sender<MatchResult>,
+    },
     Snapshot {
         resp: oneshot::Sender<(
             (Vec<SnapshotData>, Vec<SnapshotData>),
@@ -33,46 +130,239 @@ pub enum EngineMsg {
         market_id: String,
         resp: oneshot::Sender<Vec<OpenOrder>>,
     },
+    /// Simulates an order against the book without changing it. `price` is
+    /// the limit; `None` sweeps at whatever prices are resting.
+    Quote {
+        side: Side,
+        share: ShareType,
+        price: Option<Decimal>,
+        qty: Decimal,
+        resp: oneshot::Sender<Quote>,
+    },
+    /// Best bid and ask of the (yes, no) books.
+    BestPrices {
+        resp: oneshot::Sender<(BestPrices, BestPrices)>,
+    },
+    /// Stops accepting orders and removes every resting order.
+    Close {
+        resp: oneshot::Sender<ClosedBook>,
+    },
 }
 
+/// Users whose resting orders may have changed after matching `taker`.
+fn touched_users(taker: &str, trades: &[Trade]) -> HashSet<String> {
+    let mut users = HashSet::from([taker.to_string()]);
+    for t in trades {
+        users.insert(t.buyer_address.clone());
+        users.insert(t.seller_address.clone());
+    }
+    users
+}
 
+/// Refreshes the index entries of `users` from the current books.
+async fn sync_index(
+    index: &RwLock<OpenOrderIndex>,
+    market_id: u64,
+    book: &MarketBooks,
+    users: HashSet<String>,
+) {
+    let market = market_id.to_string();
+    let mut index = index.write().await;
+    for user in users {
+        index.set(&user, market_id, book.find_open_orders(&user, &market));
+    }
+}
 
-pub async fn run_market_engine(mut rx: mpsc::Receiver<EngineMsg>) {
+pub async fn run_market_engine(
+    market_id: u64,
+    index: Arc<RwLock<OpenOrderIndex>>,
+    mut rx: mpsc::Receiver<EngineMsg>,
+) {
     let mut book = MarketBooks::new();
+    let mut closed = false;
     while let Some(msg) = rx.recv().await {
         match msg {
+            EngineMsg::PlaceOrder { trades, resp, .. } if closed => {
+                let _ = resp.send((Err("market is closed".into()), trades.price));
+            }
+            EngineMsg::PlaceMarketOrder { resp, .. } if closed => {
+                let _ = resp.send(Err("market is closed".into()));
+            }
+            EngineMsg::Amend { resp, .. } if closed => {
+                let _ = resp.send(Err("market is closed".into()));
+            }
+            EngineMsg::Batch { ops, resp } if closed => {
+                let results = ops
+                    .iter()
+                    .map(|op| match op {
+                        BatchOp::Place { order, .. } => {
+                            BatchOpResult::Placed(Err("market is closed".into()), order.price)
+                        }
+                        BatchOp::Cancel { .. } => {
+                            BatchOpResult::Cancelled(Err("market is closed".into()))
+                        }
+                    })
+                    .collect();
+                let _ = resp.send(results);
+            }
+            EngineMsg::RejectMakers { resp, .. } if closed => {
+                let _ = resp.send(Err("market is closed".into()));
+            }
             EngineMsg::PlaceOrder {
                 side,
                 share,
                 trades,
                 resp,
-            } => match share {
-                ShareType::Yes => {
-                    let result = book.yes.place_order(trades, side);
-                    let _ = resp.send(result);
-                }
-
-                ShareType::No => {
-                    let result = book.no.place_order(trades, side);
-                    let _ = resp.send(result);
-                }
-            },
+            } => {
+                let taker = trades.user_address.clone();
+                let (order_id, requested) = (trades.id, trades.price);
+                let result = match share {
+                    ShareType::Yes => book.yes.place_order(trades, side),
+                    ShareType::No => book.no.place_order(trades, side),
+                };
+                let price = resting_price(&book, share, order_id, requested);
+                let trades = result
+                    .as_ref()
+                    .map(|(_, t, _)| t.as_slice())
+                    .unwrap_or_default();
+                sync_index(&index, market_id, &book, touched_users(&taker, trades)).await;
+                let _ = resp.send((result, price));
+            }
+            EngineMsg::PlaceMarketOrder {
+                side,
+                share,
+                order,
+                market,
+                resp,
+            } => {
+                let taker = order.user_address.clone();
+                let result = match share {
+                    ShareType::Yes => book.yes.place_market_order(order, side, market),
+                    ShareType::No => book.no.place_market_order(order, side, market),
+                };
+                sync_index(&index, market_id, &book, touched_users(&taker, &result.1)).await;
+                let _ = resp.send(Ok(result));
+            }
             EngineMsg::CloseOrder {
                 side,
                 share,
                 price,
                 order_id,
+                user_address,
+                resp,
+            } => {
+                let order_book = match share {
+                    ShareType::Yes => &mut book.yes,
+                    ShareType::No => &mut book.no,
+                };
+                // someone else's order looks the same as a missing one
+                let owned = order_book
+                    .find_order(order_id)
+                    .is_some_and(|o| o.user_address == user_address);
+                if !owned {
+                    let _ = resp.send((false, "Not found".to_string()));
+                    continue;
+                }
+                let result = order_book.cancel_order(side, price, order_id);
+                sync_index(&index, market_id, &book, HashSet::from([user_address])).await;
+                let _ = resp.send(result);
+            }
+            EngineMsg::Amend {
+                share,
+                order_id,
+                user_address,
+                qty,
+                price,
                 resp,
-            } => match share {
-                ShareType::Yes => {
-                    let result = book.yes.cancel_order(side, price, order_id);
-                    let _ = resp.send(result);
+            } => {
+                let result = match share {
+                    ShareType::Yes => book.yes.amend_order(order_id, &user_address, qty, price),
+                    ShareType::No => book.no.amend_order(order_id, &user_address, qty, price),
+                };
+                if let Ok(amended) = &result {
+                    let users = touched_users(&user_address, &amended.trades);
+                    sync_index(&index, market_id, &book, users).await;
                 }
-                ShareType::No => {
-                    let result = book.no.cancel_order(side, price, order_id);
-                    let _ = resp.send(result);
+                let _ = resp.send(result);
+            }
+            EngineMsg::CancelAll { filter, resp } => {
+                let removed = book.cancel_all(&filter);
+                let users = removed.iter().map(|o| o.user_address.clone()).collect();
+                sync_index(&index, market_id, &book, users).await;
+                let _ = resp.send(removed);
+            }
+            EngineMsg::Batch { ops, resp } => {
+                let mut users = HashSet::new();
+                let mut results = Vec::with_capacity(ops.len());
+                for op in ops {
+                    let result = match op {
+                        BatchOp::Place { side, share, order } => {
+                            let taker = order.user_address.clone();
+                            let (order_id, requested) = (order.id, order.price);
+                            let result = match share {
+                                ShareType::Yes => book.yes.place_order(order, side),
+                                ShareType::No => book.no.place_order(order, side),
+                            };
+                            let price = resting_price(&book, share, order_id, requested);
+                            let trades = result
+                                .as_ref()
+                                .map(|(_, t, _)| t.as_slice())
+                                .unwrap_or_default();
+                            users.extend(touched_users(&taker, trades));
+                            BatchOpResult::Placed(result, price)
+                        }
+                        BatchOp::Cancel {
+                            order_id,
+                            user_address,
+                        } => {
+                            let removed = book
+                                .cancel_owned(order_id, &user_address)
+                                .ok_or_else(|| "order not found".to_string());
+                            users.insert(user_address);
+                            BatchOpResult::Cancelled(removed)
+                        }
+                    };
+                    results.push(result);
                 }
-            },
+                sync_index(&index, market_id, &book, users).await;
+                let _ = resp.send(results);
+            }
+            EngineMsg::RejectMakers {
+                side,
+                share,
+                mut taker,
+                market,
+                maker_order_ids,
+                resp,
+            } => {
+                let order_book = match share {
+                    ShareType::Yes => &mut book.yes,
+                    ShareType::No => &mut book.no,
+                };
+                let mut removed = HashSet::new();
+                for maker_id in maker_order_ids {
+                    if let Some(maker) = order_book.remove_order(maker_id) {
+                        removed.insert(maker.user_address);
+                    }
+                }
+                // fold any resting remainder of the taker back in so it keeps a single entry
+                if let Some(resting) = order_book.remove_order(taker.id) {
+                    taker.qty += resting.qty;
+                }
+                let taker_address = taker.user_address.clone();
+                let result = match market {
+                    Some(market) => Ok(order_book.place_market_order(taker, side, market)),
+                    None => order_book.place_order(taker, side),
+                };
+                let trades = result
+                    .as_ref()
+                    .map(|(_, t, _)| t.as_slice())
+                    .unwrap_or_default();
+                let mut users = touched_users(&taker_address, trades);
+                users.extend(removed);
+                sync_index(&index, market_id, &book, users).await;
+                let _ = resp.send(result);
+            }
             EngineMsg::Snapshot { resp } => {
                 let snapshot = book.snapshot();
                 let _ = resp.send(snapshot);
@@ -81,6 +371,30 @@ pub async fn run_market_engine(mut rx: mpsc::Receiver<EngineMsg>) {
                 let open_orders = book.find_open_orders(&user_address, &market_id);
                 let _ = resp.send(open_orders);
             }
+            EngineMsg::Quote {
+                side,
+                share,
+                price,
+                qty,
+                resp,
+            } => {
+                let quote = match share {
+                    ShareType::Yes => book.yes.quote(&side, price, qty),
+                    ShareType::No => book.no.quote(&side, price, qty),
+                };
+                let _ = resp.send(quote);
+            }
+            EngineMsg::BestPrices { resp } => {
+                let _ = resp.send((book.yes.best_prices(), book.no.best_prices()));
+            }
+            EngineMsg::Close { resp } => {
+                closed = true;
+                let (yes, no) = book.snapshot();
+                let snapshot = MarketSnapshot { yes, no };
+                let (yes, no) = book.drain();
+                index.write().await.clear_market(market_id);
+                let _ = resp.send(ClosedBook { snapshot, yes, no });
+            }
         }
     }
 }
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use matching::types::OpenOrder;
+
+/// Resting orders of every user across all market engines. Each engine
+/// rewrites its own slice after any message that changes a user's orders.
+#[derive(Debug, Default)]
+pub struct OpenOrderIndex {
+    by_user: HashMap<String, HashMap<u64, Vec<OpenOrder>>>,
+}
+
+impl OpenOrderIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the user's orders in one market.
+    pub fn set(&mut self, user_address: &str, market_id: u64, orders: Vec<OpenOrder>) {
+        if orders.is_empty() {
+            if let Some(markets) = self.by_user.get_mut(user_address) {
+                markets.remove(&market_id);
+                if markets.is_empty() {
+                    self.by_user.remove(user_address);
+                }
+            }
+            return;
+        }
+        self.by_user
+            .entry(user_address.to_string())
+            .or_default()
+            .insert(market_id, orders);
+    }
+
+    /// Drops every order of a market, e.g. once its engine closes.
+    pub fn clear_market(&mut self, market_id: u64) {
+        self.by_user.retain(|_, markets| {
+            markets.remove(&market_id);
+            !markets.is_empty()
+        });
+    }
+
+    /// The user's resting orders in every market, oldest first.
+    pub fn for_user(&self, user_address: &str) -> Vec<OpenOrder> {
+        let mut orders = self
+            .by_user
+            .get(user_address)
+            .map(|markets| markets.values().flatten().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        orders.sort_by_key(|o| (o.timestamp, o.sequence));
+        orders
+    }
+}
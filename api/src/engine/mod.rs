@@ -1 +1,2 @@
-pub mod engine;
\ No newline at end of file
+pub mod engine;
+pub mod index;
\ No newline at end of file
@@ -1,7 +1,12 @@
 use std::{env, str::FromStr};
 
-use axum::{Extension, Json, extract::State, http::StatusCode};
-use db::models::market::MarketOutcome;
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use db::models::{market::MarketOutcome, transaction::TxPurpose};
+use matching::types::{CancelFilter, Outcome};
 use solana_sdk::pubkey::Pubkey;
 use tokio::sync::mpsc;
 use uuid::Uuid;
@@ -10,13 +15,16 @@ use crate::{
     engine::engine::{EngineMsg, run_market_engine},
     models::{
         admin::{
-            CreateMarketRequest, CreateMarketResponse, GetAllMarketsResponse, ResolveMarketRequest,
-            ResolveMarketResponse,
+            CancelMarketOrdersRequest, CancelMarketOrdersResponse, CreateMarketRequest,
+            CreateMarketResponse, GetAllMarketsResponse,
+            GetReconciliationResponse, GetTransactionsResponse, ReconciliationQuery,
+            ResolveMarketRequest, ResolveMarketResponse, TransactionsQuery,
         },
         auth::AuthUser,
     },
     state::state::Shared,
-    utils::s3::upload_market_metadata_to_do,
+    tracker::tracker::{submit_transaction, track_transaction},
+    utils::{market::cancel_matching_orders, s3::upload_market_metadata_to_do},
 };
 
 pub async fn create_market(
@@ -52,7 +60,7 @@ pub async fn create_market(
                 )
             })?;
     dbg!("Metadata URL:", &metadata_url);
-    state
+    let built_tx = state
         .predix_sdk
         .create_market(
             market_id,
@@ -67,15 +75,30 @@ pub async fn create_market(
                 format!("Failed to create market: {}", e),
             )
         })?;
+    submit_transaction(
+        &state,
+        &built_tx,
+        TxPurpose::CreateMarket,
+        Some(&market_id.to_string()),
+        None,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create market: {}", e),
+        )
+    })?;
     let mut markets = state.markets.write().await;
 
     let (tx, rx) = mpsc::channel::<EngineMsg>(100);
-    tokio::spawn(run_market_engine(rx));
+    tokio::spawn(run_market_engine(market_id, state.open_orders.clone(), rx));
     markets.insert(market_id, tx.clone());
     drop(markets);
 
     Ok(Json(CreateMarketResponse {
         market_id: market_id,
+        signature: built_tx.signature.to_string(),
         message: "Market created successfully".to_string(),
     }))
 }
@@ -98,7 +121,7 @@ pub async fn resolve_market(
             anchor_client_sdk::predix_program::types::MarketOutcome::Undecided
         }
     };
-    let tx = state
+    let built_tx = state
         .predix_sdk
         .set_winner(market_id, outcome)
         .await
@@ -108,12 +131,43 @@ pub async fn resolve_market(
                 format!("Failed to create set winner instruction: {}", e),
             )
         })?;
+    track_transaction(
+        &state,
+        &built_tx,
+        TxPurpose::SetWinner,
+        Some(&payload.market_id),
+        None,
+        false,
+    )
+    .await;
     Ok(Json(ResolveMarketResponse {
-        tx_message: tx,
+        tx_message: built_tx.tx_base64,
         message: "Market resolved successfully".into(),
     }))
 }
 
+/// Pulls resting orders of a whole market, optionally narrowed to one user,
+/// outcome, side or price range.
+pub async fn cancel_market_orders(
+    State(state): State<Shared>,
+    Extension(_user): Extension<AuthUser>,
+    Json(payload): Json<CancelMarketOrdersRequest>,
+) -> Result<Json<CancelMarketOrdersResponse>, (StatusCode, String)> {
+    let market_id = payload
+        .market_id
+        .parse::<u64>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
+    let filter = CancelFilter {
+        user_address: payload.user_address,
+        outcome: payload.share.map(Outcome::from),
+        side: payload.side,
+        min_price: payload.min_price,
+        max_price: payload.max_price,
+    };
+    let cancelled = cancel_matching_orders(&state, market_id, filter).await?;
+    Ok(Json(CancelMarketOrdersResponse { cancelled }))
+}
+
 pub async fn get_all_markets(
     State(state): State<Shared>,
     Extension(_user): Extension<AuthUser>,
@@ -130,3 +184,42 @@ pub async fn get_all_markets(
         })?;
     Ok(Json(GetAllMarketsResponse { markets }))
 }
+
+pub async fn get_transactions(
+    State(state): State<Shared>,
+    Extension(_user): Extension<AuthUser>,
+    Query(query): Query<TransactionsQuery>,
+) -> Result<Json<GetTransactionsResponse>, (StatusCode, String)> {
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let transactions =
+        db::queries::transaction::list_transactions(&state.db_pool, query.status, limit)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch transactions: {}", e),
+                )
+            })?;
+    Ok(Json(GetTransactionsResponse { transactions }))
+}
+
+pub async fn get_reconciliation_issues(
+    State(state): State<Shared>,
+    Extension(_user): Extension<AuthUser>,
+    Query(query): Query<ReconciliationQuery>,
+) -> Result<Json<GetReconciliationResponse>, (StatusCode, String)> {
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let issues = db::queries::reconciliation::list_issues(
+        &state.db_pool,
+        query.include_resolved.unwrap_or(false),
+        limit,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch reconciliation issues: {}", e),
+        )
+    })?;
+    Ok(Json(GetReconciliationResponse { issues }))
+}
@@ -0,0 +1,398 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anchor_client_sdk::{derive_yes_and_no_mint_pdas, utils::validate_order_amounts};
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use chrono::Utc;
+use db::models::{close_order::OrderStatus, market::MarketStatus};
+use matching::types::{OrderEntry, PostOnly, Side};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::{
+    balances::balances::{BalanceKey, reserve_for_order},
+    engine::engine::{BatchOp, BatchOpResult, EngineMsg},
+    models::{
+        auth::AuthUser,
+        orders::{BatchOpReq, BatchOpRes, BatchOrderReq, BatchOrderRes, PostOnlyMode, ShareType},
+    },
+    state::state::Shared,
+    stats::stats::refresh_book_stats,
+    utils::{
+        market::{engine_for, fetch_market, market_collateral_mint},
+        order_log::{record_amended, record_closed, record_new_order},
+        settlement::{TakerFills, settle_fills, verify_makers, withdraw_taker},
+        solana::{
+            AllowanceCheck, TradeMints, allowance_shortfalls, derive_market_pda, required_allowance,
+        },
+    },
+};
+
+/// Most operations one batch may carry; their fills settle together, split
+/// across as many transactions as they need.
+const MAX_BATCH_OPS: usize = 20;
+
+/// A place operation that passed its checks and went to the engine.
+struct AcceptedPlace {
+    order_id: Uuid,
+    side: Side,
+    share: ShareType,
+    price: Decimal,
+    qty: Decimal,
+}
+
+/// Applies place and cancel operations on one market in request order,
+/// within a single engine step, and settles every resulting fill together.
+/// An operation that fails doesn't stop the ones after it.
+pub async fn place_batch(
+    State(state): State<Shared>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<BatchOrderReq>,
+) -> Result<Json<BatchOrderRes>, (StatusCode, String)> {
+    if req.ops.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "batch is empty".into()));
+    }
+    if req.ops.len() > MAX_BATCH_OPS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("a batch holds at most {} operations", MAX_BATCH_OPS),
+        ));
+    }
+    let placed_at = Utc::now().timestamp_millis();
+    let market_id = req
+        .market_id
+        .parse::<u64>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
+    let market = fetch_market(&state.db_pool, &req.market_id).await?;
+    let places = req
+        .ops
+        .iter()
+        .any(|op| matches!(op, BatchOpReq::Place { .. }));
+    if places && (market.status != MarketStatus::Open || market.close_time <= Utc::now()) {
+        return Err((StatusCode::CONFLICT, "market is closed".into()));
+    }
+    let collateral_mint = market_collateral_mint(&market)?;
+    let share_decimals = market.share_decimals as u8;
+    let collateral_decimals = market.collateral_decimals as u8;
+    let program_id = state.predix_sdk.program_id();
+    let (market_pda, _bump) = derive_market_pda(market_id, &program_id);
+    let (yes_mint_pda, no_mint_pda) = derive_yes_and_no_mint_pdas(market_id, &program_id);
+    let mints_for = |share: ShareType| TradeMints {
+        collateral_mint,
+        collateral_decimals,
+        share_mint: match share {
+            ShareType::Yes => yes_mint_pda.0,
+            ShareType::No => no_mint_pda.0,
+        },
+        share_decimals,
+    };
+    let user_pubkey = Pubkey::from_str(&user.solana_address).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid user pubkey address: {}", e),
+        )
+    })?;
+
+    let mut results: Vec<Option<BatchOpRes>> = req.ops.iter().map(|_| None).collect();
+
+    // every place is priced up front so one RPC round trip checks all of
+    // their allowances
+    let mut checks: Vec<(usize, AllowanceCheck)> = Vec::new();
+    for (slot, op) in req.ops.iter().enumerate() {
+        let BatchOpReq::Place {
+            side,
+            share,
+            price,
+            qty,
+            ..
+        } = op
+        else {
+            continue;
+        };
+        let check = validate_order_amounts(*price, *qty, share_decimals, collateral_decimals)
+            .and_then(|_| required_allowance(user_pubkey, side, *price, *qty, &mints_for(*share)));
+        match check {
+            Ok(check) => checks.push((slot, check)),
+            Err(e) => {
+                results[slot] = Some(BatchOpRes::failed(None, format!("Invalid order: {}", e)))
+            }
+        }
+    }
+    let shortfalls = allowance_shortfalls(
+        &state.rpc_client,
+        &market_pda,
+        &checks.iter().map(|(_, c)| c.clone()).collect::<Vec<_>>(),
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Verification error: {}", e),
+        )
+    })?;
+    // shortfalls answer the checks in order, so each stays with its slot
+    let mut approved: HashMap<usize, AllowanceCheck> = HashMap::new();
+    for ((slot, check), shortfall) in checks.into_iter().zip(shortfalls) {
+        match shortfall {
+            Some(reason) => {
+                results[slot] = Some(BatchOpRes::failed(
+                    None,
+                    format!("Insufficient delegation: {}", reason),
+                ))
+            }
+            None => {
+                approved.insert(slot, check);
+            }
+        }
+    }
+
+    // reserve and record the approved places, and line every operation up in
+    // request order for the engine
+    let mut ops: Vec<BatchOp> = Vec::new();
+    let mut accepted: Vec<(usize, Option<AcceptedPlace>)> = Vec::new();
+    for (slot, op) in req.ops.iter().enumerate() {
+        match op {
+            BatchOpReq::Place {
+                side,
+                share,
+                price,
+                qty,
+                post_only,
+            } => {
+                let Some(check) = approved.remove(&slot) else {
+                    continue;
+                };
+                let order_id = Uuid::new_v4();
+                let balance_key = BalanceKey {
+                    owner: check.owner,
+                    mint: check.mint,
+                };
+                if let Err((_, reason)) =
+                    reserve_for_order(&state, balance_key, order_id, check.amount, *qty).await
+                {
+                    results[slot] = Some(BatchOpRes::failed(None, reason));
+                    continue;
+                }
+                let order = OrderEntry {
+                    post_only: post_only.map(|mode| match mode {
+                        PostOnlyMode::Reject => PostOnly::Reject,
+                        PostOnlyMode::Slide => PostOnly::Slide {
+                            tick: Decimal::new(1, collateral_decimals as u32),
+                        },
+                    }),
+                    ..OrderEntry::new(
+                        order_id,
+                        user.solana_address.clone(),
+                        market_id,
+                        side.clone(),
+                        *price,
+                        *qty,
+                        placed_at,
+                    )
+                };
+                record_new_order(&state, &market, &order, *share).await;
+                ops.push(BatchOp::Place {
+                    side: side.clone(),
+                    share: *share,
+                    order,
+                });
+                accepted.push((
+                    slot,
+                    Some(AcceptedPlace {
+                        order_id,
+                        side: side.clone(),
+                        share: *share,
+                        price: *price,
+                        qty: *qty,
+                    }),
+                ));
+            }
+            BatchOpReq::Cancel { order_id } => {
+                ops.push(BatchOp::Cancel {
+                    order_id: *order_id,
+                    user_address: user.solana_address.clone(),
+                });
+                accepted.push((slot, None));
+            }
+        }
+    }
+
+    let new_order_ids = accepted
+        .iter()
+        .filter_map(|(_, place)| place.as_ref().map(|p| p.order_id))
+        .collect::<Vec<_>>();
+    let abandon = |reason: &'static str| {
+        let state = state.clone();
+        let new_order_ids = new_order_ids.clone();
+        async move {
+            {
+                let mut balances = state.balances.lock().await;
+                for id in &new_order_ids {
+                    balances.release(*id);
+                }
+            }
+            record_closed(&state, &new_order_ids, OrderStatus::Cancelled).await;
+            (StatusCode::INTERNAL_SERVER_ERROR, reason.to_string())
+        }
+    };
+    let tx = engine_for(&state, market_id).await;
+    let engine_results = if ops.is_empty() {
+        Vec::new()
+    } else {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        if tx
+            .send(EngineMsg::Batch { ops, resp: resp_tx })
+            .await
+            .is_err()
+        {
+            return Err(abandon("engine send failed").await);
+        }
+        match resp_rx.await {
+            Ok(results) => results,
+            Err(_) => return Err(abandon("engine dropped").await),
+        }
+    };
+
+    // each operation is answered by a result of its own kind; if not, nothing
+    // can be trusted and nothing is settled
+    let in_step = engine_results.len() == accepted.len()
+        && accepted
+            .iter()
+            .zip(&engine_results)
+            .all(|((_, place), result)| {
+                place.is_some() == matches!(result, BatchOpResult::Placed(..))
+            });
+    if !in_step {
+        return Err(abandon("engine answered the batch out of order").await);
+    }
+
+    // an operation's own failure lands in its result; whatever succeeded is
+    // still released, recorded and settled below
+    let mut fills: Vec<TakerFills> = Vec::new();
+    let mut cancelled: Vec<Uuid> = Vec::new();
+    for ((slot, place), result) in accepted.into_iter().zip(engine_results) {
+        match (place, result) {
+            (Some(place), BatchOpResult::Placed(Ok((_, pending, rem)), price)) => {
+                if price != place.price {
+                    // a sliding post-only order rests off its requested price
+                    record_amended(&state, place.order_id, price, place.qty).await;
+                }
+                let mints = mints_for(place.share);
+                let verified = verify_makers(
+                    &state,
+                    &tx,
+                    &market_pda,
+                    &mints,
+                    place.share,
+                    &place.side,
+                    pending,
+                    rem,
+                    |rejected, rem| {
+                        // the engine folds in whatever still rests, so only the
+                        // quantity matched against the rejected makers goes back
+                        let rejected_qty: Decimal = rejected.iter().map(|t| t.quantity).sum();
+                        let retry = OrderEntry {
+                            original_qty: place.qty,
+                            filled_qty: place.qty - rem - rejected_qty,
+                            ..OrderEntry::new(
+                                place.order_id,
+                                user.solana_address.clone(),
+                                market_id,
+                                place.side.clone(),
+                                place.price,
+                                rejected_qty,
+                                placed_at,
+                            )
+                        };
+                        (retry, None)
+                    },
+                )
+                .await;
+                let (trades, rem) = match verified {
+                    Ok(verified) => verified,
+                    Err((_, reason)) => {
+                        withdraw_taker(
+                            &state,
+                            &tx,
+                            place.share,
+                            &place.side,
+                            price,
+                            place.order_id,
+                            &user.solana_address,
+                        )
+                        .await;
+                        results[slot] = Some(BatchOpRes::failed(Some(place.order_id), reason));
+                        continue;
+                    }
+                };
+                results[slot] = Some(BatchOpRes {
+                    success: true,
+                    order_id: Some(place.order_id),
+                    price: Some(price),
+                    trades: trades.clone(),
+                    remaining_qty: Some(rem),
+                    error: None,
+                });
+                fills.push(TakerFills {
+                    order_id: place.order_id,
+                    share: place.share,
+                    side: place.side,
+                    trades,
+                });
+            }
+            (Some(place), BatchOpResult::Placed(Err(reason), _)) => {
+                state.balances.lock().await.release(place.order_id);
+                record_closed(&state, &[place.order_id], OrderStatus::Cancelled).await;
+                results[slot] = Some(BatchOpRes::failed(Some(place.order_id), reason));
+            }
+            (None, BatchOpResult::Cancelled(Ok(order))) => {
+                cancelled.push(order.id);
+                results[slot] = Some(BatchOpRes {
+                    success: true,
+                    order_id: Some(order.id),
+                    price: Some(order.price),
+                    trades: Vec::new(),
+                    remaining_qty: None,
+                    error: None,
+                });
+            }
+            (None, BatchOpResult::Cancelled(Err(reason))) => {
+                let order_id = match &req.ops[slot] {
+                    BatchOpReq::Cancel { order_id } => Some(*order_id),
+                    BatchOpReq::Place { .. } => None,
+                };
+                results[slot] = Some(BatchOpRes::failed(order_id, reason));
+            }
+            // ruled out above
+            (Some(_), BatchOpResult::Cancelled(_)) | (None, BatchOpResult::Placed(..)) => {}
+        }
+    }
+    {
+        let mut balances = state.balances.lock().await;
+        for id in &cancelled {
+            balances.release(*id);
+        }
+    }
+    record_closed(&state, &cancelled, OrderStatus::Cancelled).await;
+    refresh_book_stats(&state, market_id).await;
+
+    // the share mint doesn't matter for settlement, only the decimals do
+    let signatures = settle_fills(
+        &state,
+        &req.market_id,
+        &mints_for(ShareType::Yes),
+        &user.solana_address,
+        &fills,
+    )
+    .await?;
+    Ok(Json(BatchOrderRes {
+        results: results
+            .into_iter()
+            .map(|res| {
+                res.unwrap_or_else(|| BatchOpRes::failed(None, "operation was not applied".into()))
+            })
+            .collect(),
+        signatures,
+    }))
+}
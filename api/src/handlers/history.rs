@@ -0,0 +1,91 @@
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use db::queries::{
+    order::list_orders_by_user, trade::list_trades_by_user, user::get_user_by_solana_address,
+};
+
+use crate::{
+    models::{
+        auth::AuthUser,
+        history::{OrderHistoryQuery, OrderHistoryResponse, TradeHistoryQuery, TradeHistoryResponse},
+    },
+    state::state::Shared,
+    utils::cursor::{decode_cursor, encode_cursor},
+};
+
+pub async fn get_order_history(
+    State(state): State<Shared>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<OrderHistoryQuery>,
+) -> Result<Json<OrderHistoryResponse>, (StatusCode, String)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let before = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    let user_id = get_user_by_solana_address(&state.db_pool, &user.solana_address)
+        .await
+        .ok()
+        .map(|u| u.id);
+    let orders = list_orders_by_user(
+        &state.db_pool,
+        user_id,
+        &user.solana_address,
+        query.market_id.as_deref(),
+        query.status,
+        query.side,
+        query.from,
+        query.to,
+        before,
+        limit,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch order history: {}", e),
+        )
+    })?;
+    let next_cursor = (orders.len() as i64 == limit)
+        .then(|| orders.last())
+        .flatten()
+        .map(|o| encode_cursor(o.order.created_at, o.order.id));
+    Ok(Json(OrderHistoryResponse {
+        orders,
+        next_cursor,
+    }))
+}
+
+pub async fn get_trade_history(
+    State(state): State<Shared>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<TradeHistoryQuery>,
+) -> Result<Json<TradeHistoryResponse>, (StatusCode, String)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let before = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    let trades = list_trades_by_user(
+        &state.db_pool,
+        &user.solana_address,
+        query.market_id.as_deref(),
+        query.side,
+        query.from,
+        query.to,
+        before,
+        limit,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch trade history: {}", e),
+        )
+    })?;
+    let next_cursor = (trades.len() as i64 == limit)
+        .then(|| trades.last())
+        .flatten()
+        .map(|t| encode_cursor(t.created_at, t.id));
+    Ok(Json(TradeHistoryResponse {
+        trades,
+        next_cursor,
+    }))
+}
@@ -1,42 +1,112 @@
-use anchor_client_sdk::{derive_yes_and_no_mint_pdas, predix_program};
+use anchor_client_sdk::{
+    derive_yes_and_no_mint_pdas,
+    utils::{fetch_mint_decimals, to_base_units},
+};
 use axum::{
     Extension, Json,
     extract::{Path, Query, State},
     http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
 };
-use db::{models::close_order::ShareType, queries::market::{self, list_markets_by_status}};
-use matching::types::Side;
-use solana_sdk::{
-    instruction::Instruction, message::Message, pubkey::Pubkey, signature::Keypair, signer::Signer,
-    transaction::Transaction,
+use chrono::{DateTime, Utc};
+use db::{
+    models::{
+        candle::CandleRow, close_order::ShareType, market::MarketSort, transaction::TxPurpose,
+    },
+    queries::{
+        candle::{last_mid_before, last_trade_price_before, mid_candles, trade_candles},
+        market::{self, MarketFilter, MarketKeyset, search_markets},
+    },
 };
+use matching::types::{Outcome, Side};
+use rust_decimal::{Decimal, RoundingStrategy};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
 use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account_idempotent};
 use spl_token::instruction::approve_checked;
-use std::{env, str::FromStr};
+use std::{collections::HashMap, convert::Infallible, str::FromStr};
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
 
 use crate::{
     models::{
         auth::AuthUser,
-        market::{ApproveRequest, ApproveRes, MarketByIdResponse, MarketsByStatusQuery, MarketsByStatusResponse},
-
+        market::{
+            ApprovalBreakdown, ApprovalItem, ApproveRequest, ApproveRes, Candle, CandleSeries,
+            CandlesQuery, CandlesResponse, MarketByIdResponse, MarketWithStats, MarketsQuery,
+            MarketsResponse,
+        },
     },
     state::state::Shared,
-    utils::solana::derive_market_pda,
+    stats::stats::stats_by_market,
+    tracker::tracker::track_transaction,
+    utils::{
+        cursor::{decode_cursor, decode_volume_cursor, encode_cursor, encode_volume_cursor},
+        market::{fetch_market, market_collateral_mint},
+        solana::derive_market_pda,
+    },
 };
 
-pub async fn get_all_markets_by_status(
+/// Public market listing with filters, full-text search and keyset pagination.
+pub async fn get_markets(
     State(state): State<Shared>,
-    Query(query): Query<MarketsByStatusQuery>,
-) -> Result<Json<MarketsByStatusResponse>, (StatusCode, String)> {
-    let markets = list_markets_by_status(&state.db_pool, query.status)
+    Query(query): Query<MarketsQuery>,
+) -> Result<Json<MarketsResponse>, (StatusCode, String)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let sort = query.sort.unwrap_or_default();
+    let after = match query.cursor.as_deref() {
+        None => None,
+        Some(cursor) if sort == MarketSort::Volume => {
+            let (volume, id) = decode_volume_cursor(cursor)?;
+            Some((MarketKeyset::Volume(volume), id))
+        }
+        Some(cursor) => {
+            let (time, id) = decode_cursor(cursor)?;
+            Some((MarketKeyset::Time(time), id))
+        }
+    };
+    let filter = MarketFilter {
+        status: query.status,
+        category: query.category.as_deref(),
+        search: query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()),
+        close_after: query.close_after,
+        close_before: query.close_before,
+    };
+    let markets = search_markets(&state.db_pool, &filter, sort, after, limit)
         .await
         .map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch markets by status: {}", e),
+                format!("Failed to fetch markets: {}", e),
             )
         })?;
-    Ok(Json(MarketsByStatusResponse { markets }))
+    let market_ids = markets.iter().map(|m| m.market_id.clone()).collect::<Vec<_>>();
+    let mut stats = stats_by_market(&state, &market_ids).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch market stats: {}", e),
+        )
+    })?;
+    let markets = markets
+        .into_iter()
+        .map(|market| MarketWithStats {
+            stats: stats.remove(&market.market_id),
+            market,
+        })
+        .collect::<Vec<_>>();
+    let next_cursor = (markets.len() as i64 == limit)
+        .then(|| markets.last())
+        .flatten()
+        .map(|m| match sort {
+            MarketSort::Newest => encode_cursor(m.market.created_at, m.market.id),
+            MarketSort::CloseTime => encode_cursor(m.market.close_time, m.market.id),
+            MarketSort::Volume => encode_volume_cursor(
+                m.stats.as_ref().map(|s| s.total_volume).unwrap_or_default(),
+                m.market.id,
+            ),
+        });
+    Ok(Json(MarketsResponse {
+        markets,
+        next_cursor,
+    }))
 }
 
 pub async fn get_market_by_id(
@@ -51,9 +121,158 @@ pub async fn get_market_by_id(
                 format!("Failed to fetch market by id: {}", e),
             )
         })?;
-    Ok(Json(MarketByIdResponse { market }))
+    let stats = stats_by_market(&state, &[market.market_id.clone()])
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch market stats: {}", e),
+            )
+        })?
+        .remove(&market.market_id);
+    Ok(Json(MarketByIdResponse { market, stats }))
+}
+
+const DEFAULT_CANDLES: i64 = 300;
+const MAX_CANDLES: i64 = 1500;
+
+/// YES and NO price history in fixed buckets, as both last-trade and mid-price
+/// candles. Buckets are aligned to the unix epoch.
+pub async fn get_market_candles(
+    State(state): State<Shared>,
+    Path(market_id): Path<String>,
+    Query(query): Query<CandlesQuery>,
+) -> Result<Json<CandlesResponse>, (StatusCode, String)> {
+    let market = fetch_market(&state.db_pool, &market_id).await?;
+    let secs = query.interval.seconds();
+    let to = query.to.unwrap_or_else(Utc::now).timestamp();
+    let to = to + (secs - to.rem_euclid(secs)) % secs;
+    let from = match query.from {
+        Some(from) => from.timestamp() - from.timestamp().rem_euclid(secs),
+        None => to - secs * query.limit.unwrap_or(DEFAULT_CANDLES).clamp(1, MAX_CANDLES),
+    };
+    if from >= to {
+        return Err((StatusCode::BAD_REQUEST, "from must be before to".into()));
+    }
+    if (to - from) / secs > MAX_CANDLES {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("range spans more than {} candles", MAX_CANDLES),
+        ));
+    }
+    let from = DateTime::from_timestamp(from, 0)
+        .ok_or((StatusCode::BAD_REQUEST, "from out of range".to_string()))?;
+    let to = DateTime::from_timestamp(to, 0)
+        .ok_or((StatusCode::BAD_REQUEST, "to out of range".to_string()))?;
+
+    let candles_err = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch candles: {}", e),
+        )
+    };
+    let yes = candle_series(&state, &market.market_id, ShareType::Yes, secs, from, to)
+        .await
+        .map_err(candles_err)?;
+    let no = candle_series(&state, &market.market_id, ShareType::No, secs, from, to)
+        .await
+        .map_err(candles_err)?;
+    Ok(Json(CandlesResponse {
+        market_id: market.market_id,
+        interval: query.interval,
+        yes,
+        no,
+    }))
+}
+
+async fn candle_series(
+    state: &Shared,
+    market_id: &str,
+    outcome: ShareType,
+    secs: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<CandleSeries, sqlx::Error> {
+    let pool = &state.db_pool;
+    let trades = trade_candles(pool, market_id, outcome.clone(), secs, from, to).await?;
+    let last_trade = last_trade_price_before(pool, market_id, outcome.clone(), from).await?;
+    let mids = mid_candles(pool, market_id, outcome.clone(), secs, from, to).await?;
+    let last_mid = last_mid_before(pool, market_id, outcome, from).await?;
+    Ok(CandleSeries {
+        last_trade: fill_gaps(trades, last_trade),
+        mid: fill_gaps(mids, last_mid),
+    })
+}
+
+/// Carries the previous close through empty buckets. Buckets before the first
+/// known price are dropped.
+fn fill_gaps(rows: Vec<CandleRow>, mut last: Option<f64>) -> Vec<Candle> {
+    let mut candles = Vec::with_capacity(rows.len());
+    for row in rows {
+        let candle = match (row.open, row.high, row.low, row.close) {
+            (Some(open), Some(high), Some(low), Some(close)) => Candle {
+                time: row.bucket_start,
+                open,
+                high,
+                low,
+                close,
+                volume: row.volume,
+                trade_count: row.trade_count,
+            },
+            _ => {
+                let Some(price) = last else {
+                    continue;
+                };
+                Candle {
+                    time: row.bucket_start,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: 0.0,
+                    trade_count: 0,
+                }
+            }
+        };
+        last = Some(candle.close);
+        candles.push(candle);
+    }
+    candles
 }
 
+/// Server-sent stream of market lifecycle events (e.g. a market closing).
+pub async fn stream_market_events(
+    State(state): State<Shared>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // a lagging subscriber skips the events it missed instead of disconnecting
+    let stream = BroadcastStream::new(state.market_events.subscribe())
+        .filter_map(|event| Event::default().json_data(event.ok()?).ok().map(Ok));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Allowance (in base units) one order needs: collateral for bids, shares for asks.
+fn approval_amount(
+    side: &Side,
+    price: Decimal,
+    qty: Decimal,
+    decimals: u8,
+) -> Result<u64, anyhow::Error> {
+    match side {
+        Side::Bid => to_base_units(price * qty, decimals, RoundingStrategy::AwayFromZero),
+        Side::Ask => to_base_units(qty, decimals, RoundingStrategy::AwayFromZero),
+    }
+}
+
+/// Builds an approve transaction that delegates to the market PDA enough to
+/// cover the new order plus every order the user already rests on the same
+/// token account. A token account has a single delegated amount, so the
+/// approval replaces the previous one rather than adding to it.
+///
+/// Bids draw on the collateral account, which every market on the same mint
+/// shares, so the amount covers the user's bids in all of them. The delegate
+/// is still this market's PDA though: bids resting in other markets can't
+/// settle until the user approves one of those again, which in turn moves
+/// the delegate away from this market.
 pub async fn delegate_approval(
     State(state): State<Shared>,
     Extension(user): Extension<AuthUser>,
@@ -61,135 +280,203 @@ pub async fn delegate_approval(
 ) -> Result<Json<ApproveRes>, (StatusCode, String)> {
     dbg!("Delegate approval payload: {:?}", &payload);
     let rpc_client = &state.rpc_client;
-    let payer_private_key = env::var("FEE_PAYER_PRIVATE_KEY").map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("FEE_PAYER_PRIVATE_KEY not set: {}", e),
-        )
-    })?;
-
-    let fee_payer = Keypair::from_base58_string(&payer_private_key);
-    let recent_blockhash = rpc_client.get_latest_blockhash().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("RPC error: {}", e),
-        )
-    })?;
-    dbg!("user wallet: {:?}", &user.solana_address);
+    let fee_payer = state.predix_sdk.fee_payer();
     let wallet_pubkey = Pubkey::from_str(&user.solana_address).map_err(|e| {
         (
             StatusCode::BAD_REQUEST,
             format!("Invalid wallet address: {}", e),
         )
     })?;
-    let usdc_mint_pubkey = Pubkey::from_str("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU")
-        .map_err(|e| {
-            (
-                StatusCode::BAD_REQUEST,
-                format!("Invalid USDC mint address: {}", e),
-            )
-        })?;
-    let market_id_str = payload.market_id.clone();
-    let market_id = market_id_str
+    let market = fetch_market(&state.db_pool, &payload.market_id).await?;
+    let collateral_mint = market_collateral_mint(&market)?;
+    let market_id = payload
+        .market_id
         .parse::<u64>()
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
-    let (market_pda, _bump) = derive_market_pda(market_id);
-    dbg!("Market PDA: {:?}", &market_pda);
-    let mut ixs: Vec<Instruction> = Vec::new();
-    let token_mint;
-    let (yes_mint_pda, no_mint_pda) = derive_yes_and_no_mint_pdas(market_id, &predix_program::ID);
-    if payload.side == Side::Bid {
-        match payload.share {
-            ShareType::Yes => {
-                token_mint = yes_mint_pda.0;
-            }
-            ShareType::No => {
-                token_mint = no_mint_pda.0;
-            }
-        }
-        let ix = create_associated_token_account_idempotent(
-            &fee_payer.pubkey(),
-            &wallet_pubkey,
-            &token_mint,
-            &spl_token::id(),
-        );
-        dbg!("Create ATA instruction: {:?}", &ix);
-        ixs.push(ix);
-        // delegate approval
-        let user_ata_pubkey = spl_associated_token_account::get_associated_token_address(
-            &wallet_pubkey,
-            &usdc_mint_pubkey,
-        );
-        let approve_ix = approve_checked(
-            &spl_token::id(),
-            &user_ata_pubkey,
-            &usdc_mint_pubkey,
-            &market_pda,
-            &wallet_pubkey,
-            &[],
-            payload.amount,
-            payload.decimals,
-        )
+    let program_id = state.predix_sdk.program_id();
+    let (market_pda, _bump) = derive_market_pda(market_id, &program_id);
+    let (yes_mint_pda, no_mint_pda) = derive_yes_and_no_mint_pdas(market_id, &program_id);
+    let (share_mint, outcome) = match payload.share {
+        ShareType::Yes => (yes_mint_pda.0, Outcome::Yes),
+        ShareType::No => (no_mint_pda.0, Outcome::No),
+    };
+    // bids on either outcome draw on the same collateral account
+    let approve_mint = match payload.side {
+        Side::Bid => collateral_mint,
+        Side::Ask => share_mint,
+    };
+    let decimals = fetch_mint_decimals(rpc_client, &approve_mint)
+        .await
         .map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to create approve instruction: {}", e),
+                format!("Failed to fetch mint decimals: {}", e),
             )
         })?;
-        dbg!("Approve instruction: {:?}", &approve_ix);
-        ixs.push(approve_ix);
-    } else if payload.side == Side::Ask {
-        match payload.share {
-            ShareType::Yes => {
-                token_mint = yes_mint_pda.0;
-            }
-            ShareType::No => {
-                token_mint = no_mint_pda.0;
+
+    let resting = state.open_orders.read().await.for_user(&user.solana_address);
+    let mut collateral_of: HashMap<String, Pubkey> =
+        HashMap::from([(payload.market_id.clone(), collateral_mint)]);
+    let mut covered = Vec::new();
+    for o in resting.into_iter().filter(|o| o.side == payload.side) {
+        let same_account = match o.side {
+            Side::Ask => o.market_id == payload.market_id && o.outcome == outcome,
+            Side::Bid => {
+                if !collateral_of.contains_key(&o.market_id) {
+                    let other = fetch_market(&state.db_pool, &o.market_id).await?;
+                    collateral_of.insert(o.market_id.clone(), market_collateral_mint(&other)?);
+                }
+                collateral_of[&o.market_id] == collateral_mint
             }
+        };
+        if same_account {
+            covered.push(o);
         }
-        let token_ata = get_associated_token_address(&wallet_pubkey, &token_mint);
-        let approve_ix = approve_checked(
-            &spl_token::id(),
-            &token_ata,
-            &token_mint,
-            &market_pda,
-            &wallet_pubkey,
-            &[],
-            payload.amount,
-            payload.decimals,
-        )
+    }
+    let mut items = covered
+        .into_iter()
+        .map(|o| {
+            Ok(ApprovalItem {
+                amount: approval_amount(&o.side, o.price, o.quantity, decimals)?,
+                order_id: Some(o.id),
+                market_id: o.market_id,
+                outcome: o.outcome,
+                side: o.side,
+                price: o.price,
+                quantity: o.quantity,
+            })
+        })
+        .collect::<Result<Vec<_>, anyhow::Error>>()
         .map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to create approve instruction: {}", e),
+                format!("Failed to compute allowance: {}", e),
             )
         })?;
-        dbg!("Approve instruction: {:?}", &approve_ix);
-        ixs.push(approve_ix);
+    items.push(ApprovalItem {
+        order_id: None,
+        market_id: payload.market_id.clone(),
+        outcome,
+        side: payload.side.clone(),
+        price: payload.price,
+        quantity: payload.qty,
+        amount: approval_amount(&payload.side, payload.price, payload.qty, decimals)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid order: {}", e)))?,
+    });
+    let total_amount = items
+        .iter()
+        .try_fold(0u64, |acc, i| acc.checked_add(i.amount))
+        .ok_or((
+            StatusCode::BAD_REQUEST,
+            "Total allowance overflows u64".to_string(),
+        ))?;
+
+    let mut ixs: Vec<Instruction> = Vec::new();
+    if payload.side == Side::Bid {
+        // the buyer needs somewhere to receive the shares
+        ixs.push(create_associated_token_account_idempotent(
+            &fee_payer,
+            &wallet_pubkey,
+            &share_mint,
+            &spl_token::id(),
+        ));
     }
+    let token_ata = get_associated_token_address(&wallet_pubkey, &approve_mint);
+    let approve_ix = approve_checked(
+        &spl_token::id(),
+        &token_ata,
+        &approve_mint,
+        &market_pda,
+        &wallet_pubkey,
+        &[],
+        total_amount,
+        decimals,
+    )
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create approve instruction: {}", e),
+        )
+    })?;
+    dbg!("Approve instruction: {:?}", &approve_ix);
+    ixs.push(approve_ix);
 
-    // we have to sign partially with fee payer and send transaction to frontend for user to sign
-    let message = Message::new(&ixs, Some(&fee_payer.pubkey()));
-    let mut tx = Transaction::new_unsigned(message);
-    dbg!("Partial transaction before signing: {:?}", &tx);
-    tx.try_partial_sign(&[fee_payer], recent_blockhash)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to partially sign transaction: {}", e),
-            )
-        })?;
-    let serialized = bincode::serialize(&tx).map_err(|e| {
+    // fee payer signs now, the user signs in their wallet
+    let built_tx = state.predix_sdk.partial_tx(&ixs, false).await.map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to serialize transaction: {}", e),
+            format!("Failed to build approve transaction: {}", e),
         )
     })?;
-    #[allow(deprecated)]
-    let tx_base64 = base64::encode(&serialized);
-    // let tx_base64 = base64::encode(&serialized);
+    track_transaction(
+        &state,
+        &built_tx,
+        TxPurpose::DelegateApproval,
+        Some(&payload.market_id),
+        Some(&user.solana_address),
+        false,
+    )
+    .await;
     Ok(Json(ApproveRes {
-        tx_message: tx_base64,
-        recent_blockhash: recent_blockhash.to_string(),
+        tx_message: built_tx.tx_base64,
+        recent_blockhash: built_tx.recent_blockhash.to_string(),
+        breakdown: ApprovalBreakdown {
+            mint: approve_mint.to_string(),
+            decimals,
+            items,
+            total_amount,
+        },
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn bucket(minute: u32, ohlc: Option<(f64, f64, f64, f64)>, volume: f64) -> CandleRow {
+        CandleRow {
+            bucket_start: Utc.with_ymd_and_hms(2025, 12, 22, 10, minute, 0).unwrap(),
+            open: ohlc.map(|c| c.0),
+            high: ohlc.map(|c| c.1),
+            low: ohlc.map(|c| c.2),
+            close: ohlc.map(|c| c.3),
+            volume,
+            trade_count: if ohlc.is_some() { 1 } else { 0 },
+        }
+    }
+
+    #[test]
+    fn empty_buckets_repeat_the_previous_close_with_no_volume() {
+        let rows = vec![
+            bucket(0, Some((0.40, 0.50, 0.35, 0.45)), 12.0),
+            bucket(1, None, 0.0),
+            bucket(2, Some((0.46, 0.46, 0.44, 0.44)), 3.0),
+        ];
+        let candles = fill_gaps(rows, None);
+        assert_eq!(candles.len(), 3);
+        let gap = &candles[1];
+        assert_eq!((gap.open, gap.high, gap.low, gap.close), (0.45, 0.45, 0.45, 0.45));
+        assert_eq!((gap.volume, gap.trade_count), (0.0, 0));
+        assert_eq!(candles[2].close, 0.44);
+    }
+
+    #[test]
+    fn leading_empty_buckets_are_dropped_without_an_earlier_price() {
+        let rows = vec![
+            bucket(0, None, 0.0),
+            bucket(1, Some((0.40, 0.40, 0.40, 0.40)), 1.0),
+        ];
+        let candles = fill_gaps(rows, None);
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].time, Utc.with_ymd_and_hms(2025, 12, 22, 10, 1, 0).unwrap());
+    }
+
+    #[test]
+    fn leading_empty_buckets_carry_the_price_from_before_the_range() {
+        let candles = fill_gaps(vec![bucket(0, None, 0.0), bucket(1, None, 0.0)], Some(0.30));
+        assert_eq!(candles.len(), 2);
+        assert!(candles.iter().all(|c| c.open == 0.30 && c.close == 0.30));
+    }
+}
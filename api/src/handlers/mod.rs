@@ -1,4 +1,7 @@
 pub mod orderbook;
+pub mod batch;
+pub mod history;
 pub mod market;
 pub mod admin;
-pub mod orders;
\ No newline at end of file
+pub mod orders;
+pub mod portfolio;
\ No newline at end of file
@@ -13,6 +13,10 @@ pub async fn get_orderbook(
     State(state): State<Shared>,
     Path(market_id): Path<u64>,
 ) -> Result<Json<MarketSnapshot>, (StatusCode, String)> {
+    if let Some(snapshot) = state.final_snapshots.read().await.get(&market_id) {
+        // closed or resolved market: serve the book as it was when trading stopped
+        return Ok(Json(snapshot.clone()));
+    }
     let markets = state.markets.read().await;
     let tx = if let Some(tx) = markets.get(&market_id) {
         tx.clone()
@@ -1,26 +1,45 @@
-use anchor_client_sdk::{
-    predix_program::types::TradeSide,
-    utils::{get_match_fills, get_remaining_accounts},
-};
+use anchor_client_sdk::{derive_yes_and_no_mint_pdas, utils::validate_order_amounts};
 use axum::{Extension, Json, extract::State, http::StatusCode};
 use chrono::prelude::*;
-use matching::types::OrderEntry;
+use db::models::{close_order::OrderStatus, market::MarketStatus, transaction::TxPurpose};
+use matching::{
+    orderbook::orderbook::OrderBook,
+    types::{
+        CancelFilter, MarketOrder, MarketOrderSize, OrderEntry, Outcome, PostOnly, PriceBound,
+        Quote, Side,
+    },
+};
+use rust_decimal::{Decimal, RoundingStrategy};
 use solana_sdk::pubkey::Pubkey;
-use std::str::FromStr;
-use tokio::sync::{mpsc, oneshot};
+use std::{collections::BTreeSet, str::FromStr};
+use tokio::sync::oneshot;
 use uuid::{Uuid, timestamp};
 
 use crate::{
-    engine::engine::{EngineMsg, run_market_engine},
+    balances::balances::{BalanceKey, reserve_for_order, resize_for_order},
+    engine::engine::EngineMsg,
     models::{
         auth::AuthUser,
         orders::{
-            CancelReq, CancelRes, MergeOrderReq, MergeOrderRes, PlaceOrderReq, PlaceOrderRes,
-            ShareType, SplitOrderReq, SplitOrderRes,
+            AmendOrderReq, AmendOrderRes, CancelAllReq, CancelAllRes, CancelReq, CancelRes,
+            MergeOrderReq, MergeOrderRes, PlaceMarketOrderReq, PlaceMarketOrderRes, PlaceOrderReq,
+            PlaceOrderRes, PostOnlyMode, QuoteReq, QuoteRes, ShareType, SplitOrderReq,
+            SplitOrderRes,
         },
     },
     state::state::Shared,
-    utils::solana::verify_delegation,
+    stats::stats::refresh_book_stats,
+    tracker::tracker::track_transaction,
+    utils::{
+        market::{
+            best_prices, cancel_matching_orders, engine_for, fetch_market, market_collateral_mint,
+        },
+        order_log::{record_amended, record_closed, record_new_order},
+        settlement::{TakerFills, settle_fills, verify_makers, withdraw_taker},
+        solana::{
+            TradeMints, derive_market_pda, find_insufficient_allowances, required_allowance,
+        },
+    },
 };
 
 pub async fn place_order(
@@ -29,104 +48,696 @@ pub async fn place_order(
     Json(req): Json<PlaceOrderReq>,
 ) -> Result<Json<PlaceOrderRes>, (StatusCode, String)> {
     let order_id = Uuid::new_v4();
+    let placed_at = Utc::now().timestamp_millis();
     let current_time = Local::now();
     println!("currenttime ---> {}",current_time.format("%Y-%m-%d %H:%M:%S"));
     let market_id_str = req.market_id.clone();
     let market_id = market_id_str
         .parse::<u64>()
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
-    let order = OrderEntry {
-        id: order_id,
-        user_address: user.solana_address.clone(),
-        market_id: market_id,
-        side: req.side.clone(),
-        price: req.price,
-        qty: req.qty,
+    let market = fetch_market(&state.db_pool, &req.market_id).await?;
+    if market.status != MarketStatus::Open || market.close_time <= Utc::now() {
+        return Err((StatusCode::CONFLICT, "market is closed".into()));
+    }
+    let collateral_mint = market_collateral_mint(&market)?;
+    let share_decimals = market.share_decimals as u8;
+    let collateral_decimals = market.collateral_decimals as u8;
+    validate_order_amounts(req.price, req.qty, share_decimals, collateral_decimals)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid order: {}", e)))?;
+    let program_id = state.predix_sdk.program_id();
+    let (market_pda, _bump) = derive_market_pda(market_id, &program_id);
+    let (yes_mint_pda, no_mint_pda) = derive_yes_and_no_mint_pdas(market_id, &program_id);
+    let mints = TradeMints {
+        collateral_mint,
+        collateral_decimals,
+        share_mint: match req.share {
+            ShareType::Yes => yes_mint_pda.0,
+            ShareType::No => no_mint_pda.0,
+        },
+        share_decimals,
     };
+    let user_pubkey = Pubkey::from_str(&user.solana_address).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid user pubkey address: {}", e),
+        )
+    })?;
 
-    let mut markets = state.markets.write().await;
-    let tx = if let Some(tx) = markets.get(&market_id) {
-        tx.clone()
-    } else {
-        let (tx, rx) = mpsc::channel::<EngineMsg>(100);
-        tokio::spawn(run_market_engine(rx));
-        markets.insert(market_id, tx.clone());
-        tx
+    // the taker must be able to cover the whole order before it touches the book
+    let taker_check = required_allowance(user_pubkey, &req.side, req.price, req.qty, &mints)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid order: {}", e)))?;
+    let taker_failures =
+        find_insufficient_allowances(&state.rpc_client, &market_pda, &[taker_check.clone()])
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Verification error: {}", e),
+                )
+            })?;
+    if let Some((_, reason)) = taker_failures.first() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Insufficient delegation: {}", reason),
+        ));
+    }
+    // lock the order's worst-case cost so other resting orders can't spend it too
+    let balance_key = BalanceKey {
+        owner: taker_check.owner,
+        mint: taker_check.mint,
+    };
+    reserve_for_order(&state, balance_key, order_id, taker_check.amount, req.qty).await?;
+
+    let order = OrderEntry {
+        post_only: req.post_only.map(|mode| match mode {
+            PostOnlyMode::Reject => PostOnly::Reject,
+            // one tick of the collateral's price precision
+            PostOnlyMode::Slide => PostOnly::Slide {
+                tick: Decimal::new(1, collateral_decimals as u32),
+            },
+        }),
+        ..OrderEntry::new(
+            order_id,
+            user.solana_address.clone(),
+            market_id,
+            req.side.clone(),
+            req.price,
+            req.qty,
+            placed_at,
+        )
     };
-    drop(markets);
+    record_new_order(&state, &market, &order, req.share).await;
+
+    let tx = engine_for(&state, market_id).await;
 
     let (resp_tx, resp_rx) = oneshot::channel();
-    tx.send(EngineMsg::PlaceOrder {
-        side: req.side,
-        share: req.share,
-        trades: order,
-        resp: resp_tx,
-    })
-    .await
-    .map_err(|_| {
-        (
+    let sent = tx
+        .send(EngineMsg::PlaceOrder {
+            side: req.side.clone(),
+            share: req.share,
+            trades: order,
+            resp: resp_tx,
+        })
+        .await;
+    if sent.is_err() {
+        state.balances.lock().await.release(order_id);
+        record_closed(&state, &[order_id], OrderStatus::Cancelled).await;
+        return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             "engine send failed".into(),
-        )
-    })?;
-    let (_id, trades, rem) = resp_rx
-        .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "engine dropped".into()))?;
-    let trade_side = match req.share {
-        ShareType::Yes => TradeSide::Yes,
-        ShareType::No => TradeSide::No,
+        ));
+    }
+    let ((_id, pending, rem), price) = match resp_rx.await {
+        Ok((Ok(result), price)) => (result, price),
+        Ok((Err(reason), _)) => {
+            state.balances.lock().await.release(order_id);
+            record_closed(&state, &[order_id], OrderStatus::Cancelled).await;
+            return Err((StatusCode::CONFLICT, reason));
+        }
+        Err(_) => {
+            state.balances.lock().await.release(order_id);
+            record_closed(&state, &[order_id], OrderStatus::Cancelled).await;
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "engine dropped".into()));
+        }
+    };
+    if price != req.price {
+        // a sliding post-only order rests off its requested price, and never
+        // trades on entry
+        record_amended(&state, order_id, price, req.qty).await;
+    }
+
+    let verified = verify_makers(
+        &state,
+        &tx,
+        &market_pda,
+        &mints,
+        req.share,
+        &req.side,
+        pending,
+        rem,
+        |rejected, rem| {
+            // the engine folds in whatever still rests, so only the quantity
+            // that was matched against the rejected makers is handed back
+            let rejected_qty: Decimal = rejected.iter().map(|t| t.quantity).sum();
+            let retry = OrderEntry {
+                original_qty: req.qty,
+                filled_qty: req.qty - rem - rejected_qty,
+                ..OrderEntry::new(
+                    order_id,
+                    user.solana_address.clone(),
+                    market_id,
+                    req.side.clone(),
+                    req.price,
+                    rejected_qty,
+                    placed_at,
+                )
+            };
+            (retry, None)
+        },
+    )
+    .await;
+    let (trades, rem) = match verified {
+        Ok(verified) => verified,
+        Err(e) => {
+            withdraw_taker(
+                &state,
+                &tx,
+                req.share,
+                &req.side,
+                price,
+                order_id,
+                &user.solana_address,
+            )
+            .await;
+            refresh_book_stats(&state, market_id).await;
+            return Err(e);
+        }
     };
+
+    refresh_book_stats(&state, market_id).await;
+
     dbg!("Trades: {:?}", &trades);
     dbg!("Remaining Qty: {:?}", &rem);
     if trades.is_empty() {
         return Ok(Json(PlaceOrderRes {
             order_id,
+            price,
             trades,
             remaining_qty: rem,
+            signatures: Vec::new(),
             message: "Order placed successfully with no matches".into(),
         }));
     }
-    verify_delegation(
-        &state.rpc_client,
-        &user.solana_address,
-        &req.collateral_mint,
-    )
-    .await
-    .map_err(|e| {
+    let fills = [TakerFills {
+        order_id,
+        share: req.share,
+        side: req.side.clone(),
+        trades: trades.clone(),
+    }];
+    let signatures =
+        settle_fills(&state, &req.market_id, &mints, &user.solana_address, &fills).await?;
+    let current_time = Local::now();
+    println!(" lastime ---> {}", current_time.format("%Y-%m-%d %H:%M:%S"));
+    Ok(Json(PlaceOrderRes {
+        order_id,
+        price,
+        trades,
+        remaining_qty: rem,
+        signatures,
+        message: "Order placed successfully".into(),
+    }))
+}
+
+/// Fills against the book right away, never past the order's price bound.
+/// Whatever can't fill is dropped instead of resting.
+pub async fn place_market_order(
+    State(state): State<Shared>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<PlaceMarketOrderReq>,
+) -> Result<Json<PlaceMarketOrderRes>, (StatusCode, String)> {
+    let order_id = Uuid::new_v4();
+    let placed_at = Utc::now().timestamp_millis();
+    let market_id = req
+        .market_id
+        .parse::<u64>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
+    let size = match req.size {
+        MarketOrderSize::Quantity(amount) | MarketOrderSize::Notional(amount) => amount,
+    };
+    if size <= Decimal::ZERO {
+        return Err((StatusCode::BAD_REQUEST, "size must be positive".into()));
+    }
+    if let PriceBound::MaxSlippage(slippage) = req.bound {
+        if slippage < Decimal::ZERO {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "slippage must not be negative".into(),
+            ));
+        }
+    }
+    let market = fetch_market(&state.db_pool, &req.market_id).await?;
+    if market.status != MarketStatus::Open || market.close_time <= Utc::now() {
+        return Err((StatusCode::CONFLICT, "market is closed".into()));
+    }
+    let collateral_mint = market_collateral_mint(&market)?;
+    let share_decimals = market.share_decimals as u8;
+    let collateral_decimals = market.collateral_decimals as u8;
+
+    let no_liquidity = || (StatusCode::CONFLICT, "no liquidity to match".to_string());
+    let tx = state
+        .markets
+        .read()
+        .await
+        .get(&market_id)
+        .cloned()
+        .ok_or_else(no_liquidity)?;
+    let (yes_top, no_top) = best_prices(&state, market_id)
+        .await
+        .ok_or_else(no_liquidity)?;
+    let top = match req.share {
+        ShareType::Yes => yes_top,
+        ShareType::No => no_top,
+    };
+    // the bound is kept on the collateral's price tick, on the cautious side
+    let worst_price = top
+        .worst_price(&req.side, req.bound)
+        .ok_or_else(no_liquidity)?
+        .round_dp_with_strategy(
+            collateral_decimals as u32,
+            match req.side {
+                Side::Bid => RoundingStrategy::ToZero,
+                Side::Ask => RoundingStrategy::AwayFromZero,
+            },
+        );
+    if worst_price <= Decimal::ZERO {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "price bound must be positive".into(),
+        ));
+    }
+    // most shares the order can trade: a notional sized order buys or sells
+    // the most shares at its worst price
+    let max_qty = match req.size {
+        MarketOrderSize::Quantity(qty) => qty,
+        MarketOrderSize::Notional(amount) => (amount / worst_price)
+            .round_dp_with_strategy(share_decimals as u32, RoundingStrategy::AwayFromZero),
+    };
+    validate_order_amounts(worst_price, max_qty, share_decimals, collateral_decimals)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid order: {}", e)))?;
+
+    let program_id = state.predix_sdk.program_id();
+    let (market_pda, _bump) = derive_market_pda(market_id, &program_id);
+    let (yes_mint_pda, no_mint_pda) = derive_yes_and_no_mint_pdas(market_id, &program_id);
+    let mints = TradeMints {
+        collateral_mint,
+        collateral_decimals,
+        share_mint: match req.share {
+            ShareType::Yes => yes_mint_pda.0,
+            ShareType::No => no_mint_pda.0,
+        },
+        share_decimals,
+    };
+    let user_pubkey = Pubkey::from_str(&user.solana_address).map_err(|e| {
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Verification error: {}", e),
+            StatusCode::BAD_REQUEST,
+            format!("Invalid user pubkey address: {}", e),
         )
     })?;
-    let market_id_str = req.market_id.clone();
-    let market_id = market_id_str
+
+    let taker_check = required_allowance(user_pubkey, &req.side, worst_price, max_qty, &mints)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid order: {}", e)))?;
+    let taker_failures =
+        find_insufficient_allowances(&state.rpc_client, &market_pda, &[taker_check.clone()])
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Verification error: {}", e),
+                )
+            })?;
+    if let Some((_, reason)) = taker_failures.first() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Insufficient delegation: {}", reason),
+        ));
+    }
+    let balance_key = BalanceKey {
+        owner: taker_check.owner,
+        mint: taker_check.mint,
+    };
+    reserve_for_order(&state, balance_key, order_id, taker_check.amount, max_qty).await?;
+
+    let order = OrderEntry::new(
+        order_id,
+        user.solana_address.clone(),
+        market_id,
+        req.side.clone(),
+        worst_price,
+        max_qty,
+        placed_at,
+    );
+    record_new_order(&state, &market, &order, req.share).await;
+
+    let qty_scale = share_decimals as u32;
+    let (resp_tx, resp_rx) = oneshot::channel();
+    let sent = tx
+        .send(EngineMsg::PlaceMarketOrder {
+            side: req.side.clone(),
+            share: req.share,
+            order,
+            market: MarketOrder {
+                size: req.size,
+                qty_scale,
+            },
+            resp: resp_tx,
+        })
+        .await;
+    if sent.is_err() {
+        state.balances.lock().await.release(order_id);
+        record_closed(&state, &[order_id], OrderStatus::Cancelled).await;
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "engine send failed".into(),
+        ));
+    }
+    let (_id, pending, rem) = match resp_rx.await {
+        Ok(Ok(result)) => result,
+        Ok(Err(reason)) => {
+            state.balances.lock().await.release(order_id);
+            record_closed(&state, &[order_id], OrderStatus::Cancelled).await;
+            return Err((StatusCode::CONFLICT, reason));
+        }
+        Err(_) => {
+            state.balances.lock().await.release(order_id);
+            record_closed(&state, &[order_id], OrderStatus::Cancelled).await;
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "engine dropped".into()));
+        }
+    };
+
+    let verified = verify_makers(
+        &state,
+        &tx,
+        &market_pda,
+        &mints,
+        req.share,
+        &req.side,
+        pending,
+        rem,
+        |rejected, _| {
+            // re-sweep only what was matched against the rejected makers
+            let rejected_qty: Decimal = rejected.iter().map(|t| t.quantity).sum();
+            let size = match req.size {
+                MarketOrderSize::Quantity(_) => MarketOrderSize::Quantity(rejected_qty),
+                MarketOrderSize::Notional(_) => {
+                    MarketOrderSize::Notional(rejected.iter().map(|t| t.price * t.quantity).sum())
+                }
+            };
+            let retry = OrderEntry::new(
+                order_id,
+                user.solana_address.clone(),
+                market_id,
+                req.side.clone(),
+                worst_price,
+                rejected_qty,
+                placed_at,
+            );
+            (retry, Some(MarketOrder { size, qty_scale }))
+        },
+    )
+    .await;
+    let (trades, rem) = match verified {
+        Ok(verified) => verified,
+        Err(e) => {
+            state.balances.lock().await.release(order_id);
+            record_closed(&state, &[order_id], OrderStatus::Cancelled).await;
+            return Err(e);
+        }
+    };
+    if let MarketOrderSize::Notional(_) = req.size {
+        // the order was recorded at the quantity its notional could buy at
+        // the bound; what it actually matched is its size
+        let matched: Decimal = trades.iter().map(|t| t.quantity).sum();
+        record_amended(&state, order_id, worst_price, matched).await;
+    }
+
+    refresh_book_stats(&state, market_id).await;
+
+    let fills = [TakerFills {
+        order_id,
+        share: req.share,
+        side: req.side.clone(),
+        trades: trades.clone(),
+    }];
+    let settled = settle_fills(&state, &req.market_id, &mints, &user.solana_address, &fills).await;
+    // nothing of a market order rests, so whatever settlement didn't release
+    // goes; the unfilled rest is dropped, a fully filled order is left as is
+    state.balances.lock().await.release(order_id);
+    record_closed(&state, &[order_id], OrderStatus::Cancelled).await;
+    let signatures = settled?;
+    let message = if trades.is_empty() {
+        "Market order matched nothing within its price bound"
+    } else {
+        "Market order filled"
+    };
+    Ok(Json(PlaceMarketOrderRes {
+        order_id,
+        trades,
+        worst_price,
+        remaining: rem,
+        signatures,
+        message: message.into(),
+    }))
+}
+
+/// Changes the size and/or price of a resting order in one engine step.
+/// Shrinking it keeps its place in the queue; a new price reprices it and may
+/// match right away.
+pub async fn amend_order(
+    State(state): State<Shared>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<AmendOrderReq>,
+) -> Result<Json<AmendOrderRes>, (StatusCode, String)> {
+    if req.qty.is_none() && req.price.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "nothing to amend".into()));
+    }
+    let market_id = req
+        .market_id
         .parse::<u64>()
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
-    let match_fills = get_match_fills(&trades, trade_side);
-    let remaining_accounts = get_remaining_accounts(&trades, trade_side, market_id);
-    state
-        .predix_sdk
-        .place_order(market_id, match_fills, remaining_accounts)
+    let market = fetch_market(&state.db_pool, &req.market_id).await?;
+    if market.status != MarketStatus::Open || market.close_time <= Utc::now() {
+        return Err((StatusCode::CONFLICT, "market is closed".into()));
+    }
+    let current = state
+        .open_orders
+        .read()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to place order on chain: {}", e),
+        .for_user(&user.solana_address)
+        .into_iter()
+        .find(|o| o.id == req.order_id && o.market_id == req.market_id)
+        .ok_or((StatusCode::NOT_FOUND, "order not found".to_string()))?;
+    let share = ShareType::from(current.outcome);
+    let new_qty = req.qty.unwrap_or(current.quantity);
+    let new_price = req.price.unwrap_or(current.price);
+    let collateral_mint = market_collateral_mint(&market)?;
+    let share_decimals = market.share_decimals as u8;
+    let collateral_decimals = market.collateral_decimals as u8;
+    validate_order_amounts(new_price, new_qty, share_decimals, collateral_decimals)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid order: {}", e)))?;
+    let program_id = state.predix_sdk.program_id();
+    let (market_pda, _bump) = derive_market_pda(market_id, &program_id);
+    let (yes_mint_pda, no_mint_pda) = derive_yes_and_no_mint_pdas(market_id, &program_id);
+    let mints = TradeMints {
+        collateral_mint,
+        collateral_decimals,
+        share_mint: match share {
+            ShareType::Yes => yes_mint_pda.0,
+            ShareType::No => no_mint_pda.0,
+        },
+        share_decimals,
+    };
+    let user_pubkey = Pubkey::from_str(&user.solana_address).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid user pubkey address: {}", e),
+        )
+    })?;
+
+    // a smaller order at the same price is covered by what it already holds;
+    // anything else is re-checked and its reservation resized up front
+    let shrinks = new_price == current.price && new_qty <= current.quantity;
+    let mut previous_reservation = None;
+    if !shrinks {
+        let check = required_allowance(user_pubkey, &current.side, new_price, new_qty, &mints)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid order: {}", e)))?;
+        let failures =
+            find_insufficient_allowances(&state.rpc_client, &market_pda, &[check.clone()])
+                .await
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Verification error: {}", e),
+                    )
+                })?;
+        if let Some((_, reason)) = failures.first() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Insufficient delegation: {}", reason),
+            ));
+        }
+        let balance_key = BalanceKey {
+            owner: check.owner,
+            mint: check.mint,
+        };
+        previous_reservation = Some(
+            resize_for_order(&state, balance_key, req.order_id, check.amount, new_qty).await?,
+        );
+    }
+    let tx = state
+        .markets
+        .read()
+        .await
+        .get(&market_id)
+        .cloned()
+        .ok_or((StatusCode::NOT_FOUND, "market not found".to_string()))?;
+    let (resp_tx, resp_rx) = oneshot::channel();
+    let sent = tx
+        .send(EngineMsg::Amend {
+            share,
+            order_id: req.order_id,
+            user_address: user.solana_address.clone(),
+            qty: req.qty,
+            price: req.price,
+            resp: resp_tx,
+        })
+        .await;
+    if sent.is_err() {
+        restore_reservation(&state, req.order_id, previous_reservation).await;
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "engine send failed".into(),
+        ));
+    }
+    let amended = match resp_rx.await {
+        Ok(Ok(amended)) => amended,
+        Ok(Err(reason)) => {
+            restore_reservation(&state, req.order_id, previous_reservation).await;
+            return Err((StatusCode::CONFLICT, reason));
+        }
+        Err(_) => {
+            restore_reservation(&state, req.order_id, previous_reservation).await;
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "engine dropped".into()));
+        }
+    };
+    let amended_qty = req.qty.unwrap_or(amended.before.qty);
+    record_amended(&state, req.order_id, amended.price, amended_qty).await;
+
+    let verified = verify_makers(
+        &state,
+        &tx,
+        &market_pda,
+        &mints,
+        share,
+        &amended.before.side,
+        amended.trades,
+        amended.remaining_qty,
+        |rejected, rem| {
+            let rejected_qty: Decimal = rejected.iter().map(|t| t.quantity).sum();
+            let retry = OrderEntry {
+                original_qty: amended.original_qty,
+                filled_qty: amended.before.filled_qty + amended_qty - rem - rejected_qty,
+                ..OrderEntry::new(
+                    req.order_id,
+                    user.solana_address.clone(),
+                    market_id,
+                    amended.before.side.clone(),
+                    amended.price,
+                    rejected_qty,
+                    amended.before.timestamp,
+                )
+            };
+            (retry, None)
+        },
+    )
+    .await;
+    let (trades, rem) = match verified {
+        Ok(verified) => verified,
+        Err(e) => {
+            withdraw_taker(
+                &state,
+                &tx,
+                share,
+                &amended.before.side,
+                amended.price,
+                req.order_id,
+                &user.solana_address,
             )
-        })?;
-    let current_time = Local::now();
-    println!(" lastime ---> {}", current_time.format("%Y-%m-%d %H:%M:%S"));
-    Ok(Json(PlaceOrderRes {
-        order_id,
-        trades,
+            .await;
+            refresh_book_stats(&state, market_id).await;
+            return Err(e);
+        }
+    };
+
+    refresh_book_stats(&state, market_id).await;
+
+    if shrinks && amended.kept_priority {
+        // the shrunk part no longer rests
+        let shrunk = amended.before.qty - amended_qty;
+        state.balances.lock().await.release_filled(req.order_id, shrunk);
+    }
+
+    let fills = [TakerFills {
+        order_id: req.order_id,
+        share,
+        side: amended.before.side.clone(),
+        trades: trades.clone(),
+    }];
+    let signatures =
+        settle_fills(&state, &req.market_id, &mints, &user.solana_address, &fills).await?;
+    Ok(Json(AmendOrderRes {
+        order_id: req.order_id,
+        price: amended.price,
         remaining_qty: rem,
-        message: "Order placed successfully".into(),
+        kept_priority: amended.kept_priority,
+        trades,
+        signatures,
+        message: "Order amended successfully".into(),
     }))
 }
 
-pub async fn cancel_order(
+// Puts back a reservation that an amend resized before the engine refused it.
+async fn restore_reservation(state: &Shared, order_id: Uuid, previous: Option<(u64, Decimal)>) {
+    if let Some((amount, qty)) = previous {
+        let _ = state.balances.lock().await.resize(order_id, amount, qty);
+    }
+}
+
+/// What placing the order would match right now. Read-only: nothing is
+/// reserved, rested or sent on chain.
+pub async fn quote_order(
     State(state): State<Shared>,
     Extension(_user): Extension<AuthUser>,
+    Json(req): Json<QuoteReq>,
+) -> Result<Json<QuoteRes>, (StatusCode, String)> {
+    if req.qty <= Decimal::ZERO {
+        return Err((StatusCode::BAD_REQUEST, "qty must be positive".into()));
+    }
+    let market = fetch_market(&state.db_pool, &req.market_id).await?;
+    let market_id = market
+        .market_id
+        .parse::<u64>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
+    let tx = state.markets.read().await.get(&market_id).cloned();
+    // no engine means no resting liquidity
+    let Some(tx) = tx else {
+        return Ok(Json(QuoteRes {
+            quote: OrderBook::new().quote(&req.side, req.price, req.qty),
+        }));
+    };
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(EngineMsg::Quote {
+        side: req.side,
+        share: req.share,
+        price: req.price,
+        qty: req.qty,
+        resp: resp_tx,
+    })
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "engine send failed".into(),
+        )
+    })?;
+    let quote: Quote = resp_rx
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "engine dropped".into()))?;
+    Ok(Json(QuoteRes { quote }))
+}
+
+pub async fn cancel_order(
+    State(state): State<Shared>,
+    Extension(user): Extension<AuthUser>,
     Json(req): Json<CancelReq>,
 ) -> Result<Json<CancelRes>, (StatusCode, String)> {
     let markets = state.markets.read().await;
@@ -146,6 +757,7 @@ pub async fn cancel_order(
         share: req.share,
         price: req.price,
         order_id: req.order_id,
+        user_address: user.solana_address.clone(),
         resp: resp_tx,
     })
     .await
@@ -159,6 +771,9 @@ pub async fn cancel_order(
         .await
         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "engine dropped".into()))?;
     if res {
+        state.balances.lock().await.release(req.order_id);
+        record_closed(&state, &[req.order_id], OrderStatus::Cancelled).await;
+        refresh_book_stats(&state, market_id).await;
         Ok(Json(CancelRes {
             success: res,
             message,
@@ -168,31 +783,62 @@ pub async fn cancel_order(
     }
 }
 
+/// Cancels every resting order of the user matching the filters, in one
+/// market or across all of their markets.
+pub async fn cancel_all_orders(
+    State(state): State<Shared>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CancelAllReq>,
+) -> Result<Json<CancelAllRes>, (StatusCode, String)> {
+    let market_ids = match &req.market_id {
+        Some(market_id) => vec![
+            market_id
+                .parse::<u64>()
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?,
+        ],
+        None => state
+            .open_orders
+            .read()
+            .await
+            .for_user(&user.solana_address)
+            .into_iter()
+            .filter_map(|o| o.market_id.parse::<u64>().ok())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+    };
+    let filter = CancelFilter {
+        user_address: Some(user.solana_address.clone()),
+        outcome: req.share.map(Outcome::from),
+        side: req.side,
+        min_price: req.min_price,
+        max_price: req.max_price,
+    };
+    let mut cancelled = Vec::new();
+    for market_id in market_ids {
+        cancelled.extend(cancel_matching_orders(&state, market_id, filter.clone()).await?);
+    }
+    Ok(Json(CancelAllRes { cancelled }))
+}
+
 pub async fn split_order(
     State(state): State<Shared>,
     Extension(user): Extension<AuthUser>,
     Json(req): Json<SplitOrderReq>,
 ) -> Result<Json<SplitOrderRes>, (StatusCode, String)> {
-    let collateral_mint = Pubkey::from_str(&req.collateral_mint).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            format!("Invalid collateral mint address: {}", e),
-        )
-    })?;
     let user_pubkey = Pubkey::from_str(&user.solana_address).map_err(|e| {
         (
             StatusCode::BAD_REQUEST,
             format!("Invalid user pubkey address: {}", e),
         )
     })?;
-    // let payer_private_key =
-    //     env::var("FEE_PAYER_PRIVATE_KEY").expect("FEE_PAYER_PRIVATE_KEY must be set");
-    // let key_pair = Keypair::from_base58_string(&payer_private_key);
     let market_id_str = req.market_id.clone();
     let market_id = market_id_str
         .parse::<u64>()
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
-    let tx = state
+    let market = fetch_market(&state.db_pool, &req.market_id).await?;
+    let collateral_mint = market_collateral_mint(&market)?;
+    let built_tx = state
         .predix_sdk
         .split_order(market_id, &user_pubkey, &collateral_mint, req.amount)
         .await
@@ -202,9 +848,18 @@ pub async fn split_order(
                 format!("Failed to create split order instruction: {}", e),
             )
         })?;
-    dbg!("Split order tx: {}", &tx);
+    dbg!("Split order tx: {}", &built_tx.tx_base64);
+    track_transaction(
+        &state,
+        &built_tx,
+        TxPurpose::Split,
+        Some(&req.market_id),
+        Some(&user.solana_address),
+        false,
+    )
+    .await;
     Ok(Json(SplitOrderRes {
-        tx_message: tx,
+        tx_message: built_tx.tx_base64,
         message: "Split order instruction created successfully".into(),
     }))
 }
@@ -214,12 +869,6 @@ pub async fn merge_order(
     Extension(user): Extension<AuthUser>,
     Json(req): Json<MergeOrderReq>,
 ) -> Result<Json<MergeOrderRes>, (StatusCode, String)> {
-    let collateral_mint = Pubkey::from_str(&req.collateral_mint).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            format!("Invalid collateral mint address: {}", e),
-        )
-    })?;
     let user_pubkey = Pubkey::from_str(&user.solana_address).map_err(|e| {
         (
             StatusCode::BAD_REQUEST,
@@ -230,7 +879,9 @@ pub async fn merge_order(
     let market_id = market_id_str
         .parse::<u64>()
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
-    let tx = state
+    let market = fetch_market(&state.db_pool, &req.market_id).await?;
+    let collateral_mint = market_collateral_mint(&market)?;
+    let built_tx = state
         .predix_sdk
         .merge_order(market_id, &user_pubkey, &collateral_mint, req.amount)
         .await
@@ -240,9 +891,18 @@ pub async fn merge_order(
                 format!("Failed to create merge order instruction: {}", e),
             )
         })?;
-    dbg!("Merge order tx: {}", &tx);
+    dbg!("Merge order tx: {}", &built_tx.tx_base64);
+    track_transaction(
+        &state,
+        &built_tx,
+        TxPurpose::Merge,
+        Some(&req.market_id),
+        Some(&user.solana_address),
+        false,
+    )
+    .await;
     Ok(Json(MergeOrderRes {
-        tx_message: tx,
+        tx_message: built_tx.tx_base64,
         message: "Merge order instruction created successfully".into(),
     }))
 }
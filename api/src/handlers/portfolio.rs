@@ -0,0 +1,246 @@
+use std::str::FromStr;
+
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use db::{
+    models::{
+        close_order::ShareType,
+        market::{MarketOutcome, MarketStatus},
+        trade::TradeSummary,
+    },
+    queries::{
+        market::{list_markets_by_ids, list_markets_by_share_mints},
+        trade::summarize_user_trades,
+    },
+};
+use matching::types::Outcome;
+use rust_decimal::{Decimal, prelude::FromPrimitive};
+use solana_account_decoder::UiAccountData;
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::Account;
+
+use crate::{
+    models::{
+        auth::AuthUser,
+        portfolio::{ClaimableWinnings, PortfolioResponse, Position, ReservedBalance},
+    },
+    state::state::Shared,
+    utils::market::best_prices,
+};
+
+/// Everything the user holds or has locked: resting orders in every market,
+/// share positions marked at the book's mid price, balances reserved by those
+/// orders, and winning shares of resolved markets.
+pub async fn get_portfolio(
+    State(state): State<Shared>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<PortfolioResponse>, (StatusCode, String)> {
+    let owner = Pubkey::from_str(&user.solana_address).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid wallet address: {}", e),
+        )
+    })?;
+    let open_orders = state.open_orders.read().await.for_user(&user.solana_address);
+    let summaries = summarize_user_trades(&state.db_pool, &user.solana_address)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch trades: {}", e),
+            )
+        })?;
+
+    // shares may come from splits or transfers as well as trades, so the
+    // markets held are found from the wallet's token accounts
+    let held_mints = held_token_mints(&state, &owner).await?;
+    let mut markets = list_markets_by_share_mints(&state.db_pool, &held_mints)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch markets: {}", e),
+            )
+        })?;
+
+    // plus markets the user has traded in or still quotes
+    let mut market_ids = summaries
+        .iter()
+        .map(|s| s.market_id.clone())
+        .chain(open_orders.iter().map(|o| o.market_id.clone()))
+        .filter(|id| !markets.iter().any(|m| &m.market_id == id))
+        .collect::<Vec<_>>();
+    market_ids.sort();
+    market_ids.dedup();
+    let traded = list_markets_by_ids(&state.db_pool, &market_ids)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch markets: {}", e),
+            )
+        })?;
+    markets.extend(traded);
+
+    let mut share_accounts = Vec::with_capacity(markets.len() * 2);
+    for market in &markets {
+        for mint in [&market.yes_mint, &market.no_mint] {
+            let mint = Pubkey::from_str(mint).map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Invalid share mint stored for market: {}", e),
+                )
+            })?;
+            share_accounts.push(get_associated_token_address(&owner, &mint));
+        }
+    }
+    let share_balances = fetch_token_amounts(&state, &share_accounts).await?;
+
+    let mut positions = Vec::new();
+    let mut claimable = Vec::new();
+    for (market, balances) in markets.iter().zip(share_balances.chunks(2)) {
+        let decimals = market.share_decimals as u32;
+        let yes = Decimal::from_i128_with_scale(balances[0] as i128, decimals);
+        let no = Decimal::from_i128_with_scale(balances[1] as i128, decimals);
+
+        if market.status == MarketStatus::Resolved {
+            let winning = match market.outcome {
+                MarketOutcome::Yes => Some((Outcome::Yes, yes)),
+                MarketOutcome::No => Some((Outcome::No, no)),
+                MarketOutcome::NotDecided => None,
+            };
+            if let Some((outcome, quantity)) = winning.filter(|(_, q)| !q.is_zero()) {
+                claimable.push(ClaimableWinnings {
+                    market_id: market.market_id.clone(),
+                    outcome,
+                    quantity,
+                    collateral_mint: market.collateral_mint.clone(),
+                });
+            }
+            continue;
+        }
+
+        let (yes_prices, no_prices) = match market.market_id.parse::<u64>() {
+            Ok(market_id) => best_prices(&state, market_id).await.unwrap_or_default(),
+            Err(_) => Default::default(),
+        };
+        for (outcome, quantity, prices) in [
+            (Outcome::Yes, yes, yes_prices),
+            (Outcome::No, no, no_prices),
+        ] {
+            if quantity.is_zero() {
+                continue;
+            }
+            let avg_entry_price = summaries
+                .iter()
+                .find(|s| s.market_id == market.market_id && same_outcome(s, outcome))
+                .filter(|s| s.bought_qty > 0.0)
+                .and_then(|s| Decimal::from_f64(s.bought_cost / s.bought_qty))
+                .map(|p| p.round_dp(6));
+            let mid_price = prices.mid();
+            let unrealized_pnl = mid_price
+                .zip(avg_entry_price)
+                .map(|(mid, entry)| (mid - entry) * quantity);
+            positions.push(Position {
+                market_id: market.market_id.clone(),
+                outcome,
+                quantity,
+                avg_entry_price,
+                mid_price,
+                unrealized_pnl,
+            });
+        }
+    }
+    let unrealized_pnl: Decimal = positions.iter().filter_map(|p| p.unrealized_pnl).sum();
+
+    let reserved = state
+        .balances
+        .lock()
+        .await
+        .reserved_for_owner(&owner)
+        .into_iter()
+        .map(|(mint, amount)| ReservedBalance {
+            mint: mint.to_string(),
+            amount,
+        })
+        .collect();
+
+    Ok(Json(PortfolioResponse {
+        open_orders,
+        positions,
+        reserved,
+        unrealized_pnl,
+        claimable,
+    }))
+}
+
+fn same_outcome(summary: &TradeSummary, outcome: Outcome) -> bool {
+    matches!(
+        (&summary.outcome, outcome),
+        (ShareType::Yes, Outcome::Yes) | (ShareType::No, Outcome::No)
+    )
+}
+
+/// Mints of every SPL token account `owner` holds a non-zero balance in.
+async fn held_token_mints(
+    state: &Shared,
+    owner: &Pubkey,
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let accounts = state
+        .rpc_client
+        .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(spl_token::id()))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch token accounts: {}", e),
+            )
+        })?;
+    let mints = accounts
+        .into_iter()
+        .filter_map(|keyed| match keyed.account.data {
+            UiAccountData::Json(account) => {
+                let info = account.parsed.get("info")?;
+                let amount = info.get("tokenAmount")?.get("amount")?.as_str()?;
+                if amount == "0" {
+                    return None;
+                }
+                info.get("mint")?.as_str().map(String::from)
+            }
+            _ => None,
+        })
+        .collect();
+    Ok(mints)
+}
+
+/// Token amounts of `accounts`, zero for accounts that don't exist.
+async fn fetch_token_amounts(
+    state: &Shared,
+    accounts: &[Pubkey],
+) -> Result<Vec<u64>, (StatusCode, String)> {
+    let mut amounts = Vec::with_capacity(accounts.len());
+    // getMultipleAccounts takes at most 100 keys per call
+    for chunk in accounts.chunks(100) {
+        let fetched = state
+            .rpc_client
+            .get_multiple_accounts(chunk)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch share balances: {}", e),
+                )
+            })?;
+        for account in fetched {
+            let amount = match account {
+                Some(account) => Account::unpack(&account.data)
+                    .map(|a| a.amount)
+                    .unwrap_or_default(),
+                None => 0,
+            };
+            amounts.push(amount);
+        }
+    }
+    Ok(amounts)
+}
@@ -1,38 +1,53 @@
-use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
-use anchor_client_sdk::PredixSdk;
-use anchor_lang::declare_program;
+use anchor_client_sdk::{PredixConfig, PredixSdk, signer::signer_from_uri};
 use aws_config::Region;
 use aws_sdk_s3::{Client as S3Client, Config, config::Credentials};
 use dotenvy::{dotenv, from_path};
-use solana_client::nonblocking::rpc_client::RpcClient;
 use std::{collections::HashMap, env, sync::Arc, path::Path};
 
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock, broadcast};
 
-use crate::state::state::AppState;
+use crate::{
+    balances::balances::{BalanceCache, run_balance_listener},
+    engine::index::OpenOrderIndex,
+    reconcile::reconcile::run_market_reconciler,
+    scheduler::{
+        prices::run_mid_price_sampler, resolution::run_resolution_listener,
+        scheduler::run_market_scheduler,
+    },
+    state::state::AppState,
+    stats::stats::run_stats_listener,
+    tracker::tracker::run_tx_tracker,
+};
 // use anchor_lang::prelude::*;
 
 mod app;
 mod auth;
+mod balances;
 mod engine;
 mod handlers;
 mod models;
+mod reconcile;
 mod routes;
+mod scheduler;
 mod state;
+mod stats;
+mod tracker;
 mod utils;
 
-declare_program!(predix_program);
-
 #[tokio::main]
 #[allow(deprecated)]
 async fn main() -> anyhow::Result<()> {
     from_path(Path::new("../.env")).ok();
 
-    let rpc_url = env::var("SOLANA_RPC_URL").expect("SOLANA_RPC_URL must be set");
-    let rpc = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
-    let payer_private_key =
-        env::var("FEE_PAYER_PRIVATE_KEY").expect("FEE_PAYER_PRIVATE_KEY must be set");
-    let predix_sdk = PredixSdk::new(&payer_private_key)?;
+    let config = PredixConfig::from_env()?;
+    let fee_payer_uri = env::var("FEE_PAYER_SIGNER").expect("FEE_PAYER_SIGNER must be set");
+    let fee_payer = signer_from_uri(&fee_payer_uri, config.signer_timeout)?;
+    // The market authority can live on a separate (colder) signer than the fee payer.
+    let authority = match env::var("AUTHORITY_SIGNER").ok().filter(|v| !v.is_empty()) {
+        Some(uri) => signer_from_uri(&uri, config.signer_timeout)?,
+        None => fee_payer.clone(),
+    };
+    let predix_sdk = PredixSdk::new(config, fee_payer, authority)?;
     let access_key = env::var("DO_SPACES_KEY").expect("DO_SPACES_KEY not set");
     let secret_key = env::var("DO_SPACES_SECRET").expect("DO_SPACES_SECRET not set");
     let endpoint = env::var("DO_SPACES_ENDPOINT").expect("DO_SPACES_ENDPOINT not set");
@@ -53,14 +68,27 @@ async fn main() -> anyhow::Result<()> {
     let db_database_url =
         env::var("DATABASE_URL").expect("DATABASE_URL environment variable not set");
     let db_pool = db::Db::new(&db_database_url).await?.pool;
+    let (market_events, _) = broadcast::channel(256);
     let state = Arc::new(AppState {
         markets: RwLock::new(HashMap::new()),
-        rpc_client: Arc::new(rpc),
+        rpc_client: predix_sdk.rpc(),
         predix_sdk: Arc::new(predix_sdk),
         s3: Arc::new(s3),
         db_pool: Arc::new(db_pool),
+        balances: Mutex::new(BalanceCache::new()),
+        open_orders: Arc::new(RwLock::new(OpenOrderIndex::new())),
+        market_events,
+        final_snapshots: RwLock::new(HashMap::new()),
     });
 
+    tokio::spawn(run_tx_tracker(state.clone()));
+    tokio::spawn(run_balance_listener(state.clone()));
+    tokio::spawn(run_market_reconciler(state.clone()));
+    tokio::spawn(run_market_scheduler(state.clone()));
+    tokio::spawn(run_resolution_listener(state.clone()));
+    tokio::spawn(run_mid_price_sampler(state.clone()));
+    tokio::spawn(run_stats_listener(state.clone()));
+
     let app = app::build_app(state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3030").await.unwrap();
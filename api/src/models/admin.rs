@@ -1,5 +1,14 @@
-use db::models::market::{Market, MarketOutcome};
+use db::models::{
+    market::{Market, MarketOutcome},
+    reconciliation::ReconciliationIssue,
+    transaction::{TrackedTransaction, TxStatus},
+};
+use matching::types::Side;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::orders::ShareType;
 
 #[derive(Deserialize, Debug)]
 pub struct CreateMarketRequest {
@@ -19,6 +28,7 @@ pub struct MarketMetadata {
 #[derive(Serialize, Debug)]
 pub struct CreateMarketResponse {
     pub market_id: u64,
+    pub signature: String,
     pub message: String,
 }
 
@@ -37,3 +47,41 @@ pub struct ResolveMarketResponse {
     pub tx_message: String,
     pub message: String,
 }
+
+/// Cancels orders of a whole market; an unset filter matches every order.
+#[derive(Deserialize, Debug)]
+pub struct CancelMarketOrdersRequest {
+    pub market_id: String,
+    pub user_address: Option<String>,
+    pub share: Option<ShareType>,
+    pub side: Option<Side>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CancelMarketOrdersResponse {
+    pub cancelled: Vec<Uuid>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TransactionsQuery {
+    pub status: Option<TxStatus>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GetTransactionsResponse {
+    pub transactions: Vec<TrackedTransaction>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReconciliationQuery {
+    pub include_resolved: Option<bool>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GetReconciliationResponse {
+    pub issues: Vec<ReconciliationIssue>,
+}
@@ -0,0 +1,16 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Market lifecycle changes pushed to `/markets/events` subscribers.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MarketEvent {
+    MarketClosed {
+        market_id: String,
+        cancelled_order_ids: Vec<Uuid>,
+    },
+    MarketResolved {
+        market_id: String,
+        cancelled_order_ids: Vec<Uuid>,
+    },
+}
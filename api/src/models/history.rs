@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use db::models::{
+    close_order::{OrderHistoryEntry, OrderSide, OrderStatus},
+    trade::TradeRecord,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug)]
+pub struct OrderHistoryQuery {
+    pub market_id: Option<String>,
+    pub status: Option<OrderStatus>,
+    pub side: Option<OrderSide>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OrderHistoryResponse {
+    pub orders: Vec<OrderHistoryEntry>,
+    /// Pass back as `cursor` to fetch the next page; absent on the last page.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TradeHistoryQuery {
+    pub market_id: Option<String>,
+    /// The user's side of the fill: `bid` for buys, `ask` for sells.
+    pub side: Option<OrderSide>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TradeHistoryResponse {
+    pub trades: Vec<TradeRecord>,
+    pub next_cursor: Option<String>,
+}
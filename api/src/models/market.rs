@@ -1,35 +1,142 @@
+use chrono::{DateTime, Utc};
 use db::models::{
     close_order::ShareType,
-    market::{Market, MarketStatus},
+    market::{Market, MarketSort, MarketStatus},
+    market_stats::MarketStats,
 };
-use matching::types::Side;
+use matching::types::{Outcome, Side};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A market with its trading stats; `stats` is `None` until it sees activity.
+#[derive(Serialize, Debug)]
+pub struct MarketWithStats {
+    #[serde(flatten)]
+    pub market: Market,
+    pub stats: Option<MarketStats>,
+}
 
 #[derive(Serialize, Debug)]
 pub struct MarketByIdResponse {
     pub market: Market,
+    pub stats: Option<MarketStats>,
 }
 
+/// Every filter is optional. `q` is a web-search style query over title and
+/// description; `close_after`/`close_before` bound the close time.
 #[derive(Deserialize, Debug)]
-pub struct MarketsByStatusQuery {
-    pub status: MarketStatus,
+pub struct MarketsQuery {
+    pub status: Option<MarketStatus>,
+    pub category: Option<String>,
+    pub q: Option<String>,
+    pub close_after: Option<DateTime<Utc>>,
+    pub close_before: Option<DateTime<Utc>>,
+    pub sort: Option<MarketSort>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
 }
 
 #[derive(Serialize, Debug)]
-pub struct MarketsByStatusResponse {
-    pub markets: Vec<Market>,
+pub struct MarketsResponse {
+    pub markets: Vec<MarketWithStats>,
+    pub next_cursor: Option<String>,
 }
+/// The order the user is about to place; the allowance also covers every
+/// order they already have resting on the same token account.
 #[derive(Deserialize, Debug)]
 pub struct ApproveRequest {
     pub market_id: String,
     pub side: Side, // "bid" or "ask"
     pub share: ShareType,
+    pub price: Decimal,
+    pub qty: Decimal,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApprovalItem {
+    /// `None` for the order being approved.
+    pub order_id: Option<Uuid>,
+    /// Market the order rests in; bids of other markets share the collateral.
+    pub market_id: String,
+    pub outcome: Outcome,
+    pub side: Side,
+    pub price: Decimal,
+    pub quantity: Decimal,
     pub amount: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApprovalBreakdown {
+    pub mint: String,
     pub decimals: u8,
+    pub items: Vec<ApprovalItem>,
+    pub total_amount: u64,
 }
 
 #[derive(Serialize, Debug)]
 pub struct ApproveRes {
     pub tx_message: String,
     pub recent_blockhash: String,
+    pub breakdown: ApprovalBreakdown,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+pub enum CandleInterval {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl CandleInterval {
+    pub fn seconds(self) -> i64 {
+        match self {
+            CandleInterval::OneMinute => 60,
+            CandleInterval::FiveMinutes => 5 * 60,
+            CandleInterval::OneHour => 60 * 60,
+            CandleInterval::OneDay => 24 * 60 * 60,
+        }
+    }
+}
+
+/// `from` defaults to `limit` candles before `to`, which defaults to now.
+#[derive(Deserialize, Debug)]
+pub struct CandlesQuery {
+    pub interval: CandleInterval,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// A bucket without activity repeats the previous close with zero volume.
+#[derive(Serialize, Debug)]
+pub struct Candle {
+    pub time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub trade_count: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CandleSeries {
+    /// Candles of confirmed trade prices.
+    pub last_trade: Vec<Candle>,
+    /// Candles of the sampled book mid price.
+    pub mid: Vec<Candle>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CandlesResponse {
+    pub market_id: String,
+    pub interval: CandleInterval,
+    pub yes: CandleSeries,
+    pub no: CandleSeries,
 }
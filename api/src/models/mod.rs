@@ -1,5 +1,8 @@
 pub mod orderbook;  
 pub mod auth;
+pub mod events;
+pub mod history;
 pub mod market;
 pub mod admin;
-pub mod orders;
\ No newline at end of file
+pub mod orders;
+pub mod portfolio;
\ No newline at end of file
@@ -1,4 +1,4 @@
-use matching::types::{Side, Trade};
+use matching::types::{MarketOrderSize, Outcome, PriceBound, Quote, Side, Trade};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -10,28 +10,175 @@ pub enum ShareType {
     No,
 }
 
+impl From<ShareType> for Outcome {
+    fn from(share: ShareType) -> Self {
+        match share {
+            ShareType::Yes => Outcome::Yes,
+            ShareType::No => Outcome::No,
+        }
+    }
+}
+
+impl From<Outcome> for ShareType {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Yes => ShareType::Yes,
+            Outcome::No => ShareType::No,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct PlaceOrderReq {
     pub market_id: String,
-    pub collateral_mint: String,
     pub side: Side, // "bid" or "ask"
     pub share: ShareType,
     pub price: Decimal,
     pub qty: Decimal,
+    /// Makes the order maker-only.
+    pub post_only: Option<PostOnlyMode>,
+}
+
+/// What to do with a post-only order that would take liquidity.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PostOnlyMode {
+    Reject,
+    /// Reprice it one tick inside the best opposite price.
+    Slide,
 }
 
 #[derive(Serialize)]
 pub struct PlaceOrderRes {
     pub order_id: Uuid,
+    /// Price the order rests at; a sliding post-only order may have moved.
+    pub price: Decimal,
     pub trades: Vec<Trade>,
     pub remaining_qty: Decimal,
+    /// Transactions settling the fills, in order; a large match takes several.
+    pub signatures: Vec<String>,
+    pub message: String,
+}
+
+/// Filters of a mass cancel; an unset filter matches every order. Without a
+/// market it covers every market the user has orders in.
+#[derive(Deserialize)]
+pub struct CancelAllReq {
+    pub market_id: Option<String>,
+    pub share: Option<ShareType>,
+    pub side: Option<Side>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+}
+
+#[derive(Serialize)]
+pub struct CancelAllRes {
+    pub cancelled: Vec<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct BatchOrderReq {
+    pub market_id: String,
+    pub ops: Vec<BatchOpReq>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOpReq {
+    Place {
+        side: Side,
+        share: ShareType,
+        price: Decimal,
+        qty: Decimal,
+        post_only: Option<PostOnlyMode>,
+    },
+    Cancel {
+        order_id: Uuid,
+    },
+}
+
+/// Result of one batch operation, in request order.
+#[derive(Serialize)]
+pub struct BatchOpRes {
+    pub success: bool,
+    /// The placed or cancelled order; `None` if a place was refused before
+    /// an order was created.
+    pub order_id: Option<Uuid>,
+    /// Price a placed order rests at.
+    pub price: Option<Decimal>,
+    pub trades: Vec<Trade>,
+    pub remaining_qty: Option<Decimal>,
+    pub error: Option<String>,
+}
+
+impl BatchOpRes {
+    pub fn failed(order_id: Option<Uuid>, error: String) -> Self {
+        Self {
+            success: false,
+            order_id,
+            price: None,
+            trades: Vec::new(),
+            remaining_qty: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BatchOrderRes {
+    pub results: Vec<BatchOpRes>,
+    /// Transactions settling the fills of every operation, in order.
+    pub signatures: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AmendOrderReq {
+    pub market_id: String,
+    pub order_id: Uuid,
+    /// New open quantity.
+    pub qty: Option<Decimal>,
+    pub price: Option<Decimal>,
+}
+
+#[derive(Serialize)]
+pub struct AmendOrderRes {
+    pub order_id: Uuid,
+    pub price: Decimal,
+    /// Quantity left resting after any immediate fills.
+    pub remaining_qty: Decimal,
+    /// False if the order lost its place in the queue.
+    pub kept_priority: bool,
+    pub trades: Vec<Trade>,
+    /// Transactions settling the fills, in order; a large match takes several.
+    pub signatures: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct PlaceMarketOrderReq {
+    pub market_id: String,
+    pub side: Side,
+    pub share: ShareType,
+    pub size: MarketOrderSize,
+    pub bound: PriceBound,
+}
+
+#[derive(Serialize)]
+pub struct PlaceMarketOrderRes {
+    pub order_id: Uuid,
+    pub trades: Vec<Trade>,
+    /// The price bound the order swept to.
+    pub worst_price: Decimal,
+    /// Unfilled part of the order, in the unit it was sized in. It does not rest.
+    pub remaining: Decimal,
+    /// Transactions settling the fills, in order; a large match takes several.
+    pub signatures: Vec<String>,
     pub message: String,
 }
 
 #[derive(Deserialize)]
 pub struct SplitOrderReq {
     pub market_id: String,
-    pub collateral_mint: String,
     pub amount: u64,
 }
 
@@ -44,7 +191,6 @@ pub struct SplitOrderRes {
 #[derive(Deserialize)]
 pub struct MergeOrderReq {
     pub market_id: String,
-    pub collateral_mint: String,
     pub amount: u64,
 }
 
@@ -67,3 +213,20 @@ pub struct CancelRes {
     pub success: bool,
     pub message: String,
 }
+
+
+/// An order to simulate; without a `price` it is priced like a market order.
+#[derive(Deserialize)]
+pub struct QuoteReq {
+    pub market_id: String,
+    pub side: Side,
+    pub share: ShareType,
+    pub price: Option<Decimal>,
+    pub qty: Decimal,
+}
+
+#[derive(Serialize)]
+pub struct QuoteRes {
+    #[serde(flatten)]
+    pub quote: Quote,
+}
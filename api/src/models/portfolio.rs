@@ -0,0 +1,41 @@
+use matching::types::{OpenOrder, Outcome};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Shares of one outcome held in a market that hasn't been resolved yet.
+#[derive(Serialize, Debug)]
+pub struct Position {
+    pub market_id: String,
+    pub outcome: Outcome,
+    pub quantity: Decimal,
+    /// Average price paid on the book; `None` if the shares came from splits.
+    pub avg_entry_price: Option<Decimal>,
+    pub mid_price: Option<Decimal>,
+    /// `(mid_price - avg_entry_price) * quantity`, when both prices are known.
+    pub unrealized_pnl: Option<Decimal>,
+}
+
+/// Part of a token balance locked by resting orders, in base units.
+#[derive(Serialize, Debug)]
+pub struct ReservedBalance {
+    pub mint: String,
+    pub amount: u64,
+}
+
+/// Winning shares of a resolved market that can be redeemed for collateral.
+#[derive(Serialize, Debug)]
+pub struct ClaimableWinnings {
+    pub market_id: String,
+    pub outcome: Outcome,
+    pub quantity: Decimal,
+    pub collateral_mint: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PortfolioResponse {
+    pub open_orders: Vec<OpenOrder>,
+    pub positions: Vec<Position>,
+    pub reserved: Vec<ReservedBalance>,
+    pub unrealized_pnl: Decimal,
+    pub claimable: Vec<ClaimableWinnings>,
+}
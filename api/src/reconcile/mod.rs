@@ -0,0 +1 @@
+pub mod reconcile;
\ No newline at end of file
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use anchor_client_sdk::{MarketAccount, predix_program::types::MarketOutcome as ChainOutcome};
+use db::{
+    models::market::{Market, MarketOutcome, MarketStatus},
+    queries::{
+        market::list_all_markets,
+        market_stats::set_open_interest,
+        reconciliation::{record_mismatch, resolve_mismatches},
+    },
+};
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use spl_token::state::{Account, Mint};
+
+use crate::state::state::Shared;
+
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A field whose database (or market account) value disagrees with the chain.
+struct Mismatch {
+    field: &'static str,
+    expected: String,
+    actual: String,
+}
+
+impl Mismatch {
+    fn new(field: &'static str, expected: impl ToString, actual: impl ToString) -> Self {
+        Self {
+            field,
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }
+    }
+}
+
+/// Periodically compares every market row with its on-chain account and
+/// records the differences for admins.
+pub async fn run_market_reconciler(state: Shared) {
+    let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = reconcile_markets(&state).await {
+            println!("Market reconciliation error: {}", e);
+        }
+    }
+}
+
+async fn reconcile_markets(state: &Shared) -> anyhow::Result<()> {
+    for market in list_all_markets(&state.db_pool).await? {
+        let mismatches = match reconcile_market(state, &market).await {
+            Ok(mismatches) => mismatches,
+            Err(e) => {
+                println!("Failed to reconcile market {}: {}", market.market_id, e);
+                continue;
+            }
+        };
+        for m in &mismatches {
+            record_mismatch(
+                &state.db_pool,
+                &market.market_id,
+                m.field,
+                &m.expected,
+                &m.actual,
+            )
+            .await?;
+        }
+        let still_open = mismatches
+            .iter()
+            .map(|m| m.field.to_string())
+            .collect::<Vec<_>>();
+        resolve_mismatches(&state.db_pool, &market.market_id, &still_open).await?;
+    }
+    Ok(())
+}
+
+async fn reconcile_market(state: &Shared, market: &Market) -> anyhow::Result<Vec<Mismatch>> {
+    let market_id = market.market_id.parse::<u64>()?;
+    let chain = match state.predix_sdk.fetch_market(market_id).await {
+        Ok(chain) => chain,
+        Err(e) => return Ok(vec![Mismatch::new("account", "present", format!("unreadable: {}", e))]),
+    };
+    if let Err(e) = set_open_interest(
+        &state.db_pool,
+        &market.market_id,
+        chain.yes_total as i64,
+        chain.no_total as i64,
+    )
+    .await
+    {
+        println!("Failed to update open interest of market {}: {}", market.market_id, e);
+    }
+    let mut mismatches = Vec::new();
+
+    let addresses = [
+        ("yes_mint", &market.yes_mint, chain.yes_mint),
+        ("no_mint", &market.no_mint, chain.no_mint),
+        ("collateral_vault", &market.usdc_vault, chain.collateral_vault),
+        ("collateral_mint", &market.collateral_mint, chain.collateral_mint),
+    ];
+    for (field, expected, actual) in addresses {
+        if expected.parse::<Pubkey>().ok() != Some(actual) {
+            mismatches.push(Mismatch::new(field, expected, actual));
+        }
+    }
+
+    let db_settled = market.status == MarketStatus::Resolved;
+    if db_settled != chain.is_settled {
+        let chain_status = if chain.is_settled { "settled" } else { "unsettled" };
+        mismatches.push(Mismatch::new("status", format!("{:?}", market.status), chain_status));
+    }
+    let chain_outcome = match chain.outcome {
+        ChainOutcome::Yes => MarketOutcome::Yes,
+        ChainOutcome::No => MarketOutcome::No,
+        ChainOutcome::Undecided => MarketOutcome::NotDecided,
+    };
+    if market.outcome != chain_outcome {
+        mismatches.push(Mismatch::new(
+            "outcome",
+            format!("{:?}", market.outcome),
+            format!("{:?}", chain_outcome),
+        ));
+    }
+
+    mismatches.extend(reconcile_balances(state, market, &chain).await?);
+    Ok(mismatches)
+}
+
+/// Checks the market's share totals against the mint supplies and, while the
+/// market is unsettled, the vault against the collateral backing those shares.
+async fn reconcile_balances(
+    state: &Shared,
+    market: &Market,
+    chain: &MarketAccount,
+) -> anyhow::Result<Vec<Mismatch>> {
+    let accounts = state
+        .rpc_client
+        .get_multiple_accounts(&[chain.yes_mint, chain.no_mint, chain.collateral_vault])
+        .await?;
+    let [yes_mint, no_mint, vault] = <[_; 3]>::try_from(accounts)
+        .map_err(|_| anyhow::anyhow!("unexpected account count"))?;
+    let mut mismatches = Vec::new();
+
+    let yes_supply = yes_mint.map(|a| Mint::unpack(&a.data)).transpose()?.map(|m| m.supply);
+    if yes_supply != Some(chain.yes_total) {
+        mismatches.push(Mismatch::new(
+            "yes_supply",
+            chain.yes_total,
+            format!("{:?}", yes_supply),
+        ));
+    }
+    let no_supply = no_mint.map(|a| Mint::unpack(&a.data)).transpose()?.map(|m| m.supply);
+    if no_supply != Some(chain.no_total) {
+        mismatches.push(Mismatch::new(
+            "no_supply",
+            chain.no_total,
+            format!("{:?}", no_supply),
+        ));
+    }
+
+    // every split deposits one unit of collateral per YES/NO pair; once settled,
+    // claims drain the vault unevenly so there is nothing to compare against
+    if !chain.is_settled {
+        let vault_balance = vault.map(|a| Account::unpack(&a.data)).transpose()?.map(|v| v.amount);
+        let expected = chain.yes_total as u128 * 10u128.pow(market.collateral_decimals as u32)
+            / 10u128.pow(market.share_decimals as u32);
+        if vault_balance.map(u128::from) != Some(expected) {
+            mismatches.push(Mismatch::new(
+                "vault_balance",
+                expected,
+                format!("{:?}", vault_balance),
+            ));
+        }
+    }
+    Ok(mismatches)
+}
@@ -4,7 +4,10 @@ use axum::{Router, middleware::from_fn, routing::{get, post}};
 
 use crate::{
     auth::{auth::auth_middleware, require_admin::require_admin},
-    handlers::{admin::{create_market, get_all_markets, resolve_market}},
+    handlers::admin::{
+        cancel_market_orders, create_market, get_all_markets, get_reconciliation_issues,
+        get_transactions, resolve_market,
+    },
     state::state::AppState,
 };
 
@@ -12,7 +15,10 @@ pub fn router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/market/create", post(create_market))
         .route("/market/set-winner", post(resolve_market))
+        .route("/market/cancel-all", post(cancel_market_orders))
         .route("/markets", get(get_all_markets))
+        .route("/transactions", get(get_transactions))
+        .route("/reconciliation", get(get_reconciliation_issues))
         .route_layer(from_fn(require_admin))
         .route_layer(from_fn(auth_middleware))
 }
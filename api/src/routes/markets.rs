@@ -5,13 +5,15 @@ use axum::{
 };
 
 use crate::{
-    auth::auth::auth_middleware, handlers::market::{delegate_approval, get_all_markets_by_status, get_market_by_id}, state::state::AppState
+    auth::auth::auth_middleware, handlers::market::{delegate_approval, get_markets, get_market_by_id, get_market_candles, stream_market_events}, state::state::AppState
 };
 
 pub fn router() -> Router<Arc<AppState>> {
     let public = Router::new()
-        .route("/", get(get_all_markets_by_status))
-        .route("/{id}", get(get_market_by_id));
+        .route("/", get(get_markets))
+        .route("/events", get(stream_market_events))
+        .route("/{id}", get(get_market_by_id))
+        .route("/{id}/candles", get(get_market_candles));
 
     let protected = Router::new()
         .route("/delegate", post(delegate_approval))
@@ -1,4 +1,5 @@
 pub mod admin;
 pub mod markets;
 pub mod orderbook;
-pub mod orders;
\ No newline at end of file
+pub mod orders;
+pub mod portfolio;
\ No newline at end of file
@@ -2,17 +2,24 @@ use std::sync::Arc;
 
 use axum::{Router, middleware::from_fn};
 
-use crate::{ auth::auth::auth_middleware, handlers::orders::{cancel_order, merge_order, place_order, split_order}, state::state::AppState};
+use crate::{ auth::auth::auth_middleware, handlers::{batch::place_batch, history::{get_order_history, get_trade_history}, orders::{amend_order, cancel_all_orders, cancel_order, merge_order, place_market_order, place_order, quote_order, split_order}}, state::state::AppState};
 
-use axum::routing::{delete, post};
+use axum::routing::{delete, get, post};
 
 
 
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/place", post(place_order))
+        .route("/market", post(place_market_order))
+        .route("/quote", post(quote_order))
+        .route("/amend", post(amend_order))
+        .route("/batch", post(place_batch))
         .route("/split", post(split_order))
         .route("/merge", post(merge_order))
         .route("/cancel/{order_id}", delete(cancel_order))
+        .route("/cancel-all", post(cancel_all_orders))
+        .route("/history", get(get_order_history))
+        .route("/trades", get(get_trade_history))
         .route_layer(from_fn(auth_middleware))
 }
\ No newline at end of file
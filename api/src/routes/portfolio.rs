@@ -0,0 +1,11 @@
+use std::sync::Arc;
+
+use axum::{Router, middleware::from_fn, routing::get};
+
+use crate::{auth::auth::auth_middleware, handlers::portfolio::get_portfolio, state::state::AppState};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_portfolio))
+        .route_layer(from_fn(auth_middleware))
+}
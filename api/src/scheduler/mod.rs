@@ -0,0 +1,3 @@
+pub mod prices;
+pub mod resolution;
+pub mod scheduler;
\ No newline at end of file
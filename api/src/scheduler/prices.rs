@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use db::{models::close_order::ShareType, queries::candle::record_mid_price};
+use matching::types::BestPrices;
+
+use crate::{
+    state::state::Shared,
+    stats::stats::refresh_book_stats,
+    utils::{market::best_prices, order_log::to_f64},
+};
+
+const MID_SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Samples the top of every running book so mid-price candles can be built
+/// for periods without trades, and keeps the best bid/ask in market stats.
+pub async fn run_mid_price_sampler(state: Shared) {
+    let mut interval = tokio::time::interval(MID_SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+        sample_mid_prices(&state).await;
+    }
+}
+
+async fn sample_mid_prices(state: &Shared) {
+    let market_ids = state.markets.read().await.keys().copied().collect::<Vec<_>>();
+    for market_id in market_ids {
+        refresh_book_stats(state, market_id).await;
+        let Some((yes, no)) = best_prices(state, market_id).await else {
+            continue;
+        };
+        for (outcome, prices) in [(ShareType::Yes, yes), (ShareType::No, no)] {
+            if let Err(e) = record_sample(state, market_id, outcome, prices).await {
+                println!("Failed to record mid price of market {}: {}", market_id, e);
+            }
+        }
+    }
+}
+
+async fn record_sample(
+    state: &Shared,
+    market_id: u64,
+    outcome: ShareType,
+    prices: BestPrices,
+) -> Result<(), sqlx::Error> {
+    // a one-sided book has no mid
+    let (Some(bid), Some(ask), Some(mid)) = (prices.best_bid, prices.best_ask, prices.mid()) else {
+        return Ok(());
+    };
+    record_mid_price(
+        &state.db_pool,
+        &market_id.to_string(),
+        outcome,
+        to_f64(bid),
+        to_f64(ask),
+        to_f64(mid),
+    )
+    .await
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use db::{
+    models::{
+        close_order::{OrderStatus, ShareType},
+        market::Market,
+    },
+    queries::{
+        market::get_market_by_id, market_event::MARKET_SETTLED_CHANNEL,
+        order::{close_open_order, create_close_order},
+        user::get_user_by_solana_address,
+    },
+};
+use matching::types::OrderEntry;
+use rust_decimal::prelude::ToPrimitive;
+use sqlx::postgres::PgListener;
+
+use crate::{models::events::MarketEvent, scheduler::scheduler::halt_engine, state::state::Shared};
+
+/// Listens for settlements published by the event listener and retires the
+/// settled markets' engines.
+pub async fn run_resolution_listener(state: Shared) {
+    loop {
+        if let Err(e) = listen_market_settled(&state).await {
+            println!("Resolution listener error: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn listen_market_settled(state: &Shared) -> anyhow::Result<()> {
+    let mut listener = PgListener::connect_with(&state.db_pool).await?;
+    listener.listen(MARKET_SETTLED_CHANNEL).await?;
+    loop {
+        let notification = listener.recv().await?;
+        let market_id = notification.payload();
+        if let Err(e) = retire_market_engine(state, market_id).await {
+            println!("Failed to retire engine of market {}: {}", market_id, e);
+        }
+    }
+}
+
+/// Resolution hook: cancels every resting order, archives them as cancelled,
+/// keeps a final read-only snapshot of the book and drops the engine's sender
+/// so its task ends. A market the scheduler already closed was halted and
+/// snapshotted then, so only its engine is dropped.
+pub async fn retire_market_engine(state: &Shared, market_id: &str) -> anyhow::Result<()> {
+    let market = get_market_by_id(&state.db_pool, market_id.to_string()).await?;
+    let id = market_id.parse::<u64>()?;
+    if state.final_snapshots.read().await.contains_key(&id) {
+        state.markets.write().await.remove(&id);
+        let _ = state.market_events.send(MarketEvent::MarketResolved {
+            market_id: market_id.to_string(),
+            cancelled_order_ids: Vec::new(),
+        });
+        return Ok(());
+    }
+    let Some(closed) = halt_engine(state, id).await else {
+        return Ok(());
+    };
+    // with its last sender gone the engine's receive loop ends
+    state.markets.write().await.remove(&id);
+    state
+        .final_snapshots
+        .write()
+        .await
+        .insert(id, closed.snapshot.clone());
+
+    let closed_at = Utc::now();
+    let cancelled = closed
+        .yes
+        .iter()
+        .map(|o| (ShareType::Yes, o))
+        .chain(closed.no.iter().map(|o| (ShareType::No, o)));
+    for (share, order) in cancelled {
+        if let Err(e) = archive_cancelled_order(state, &market, share, order, closed_at).await {
+            println!("Failed to archive cancelled order {}: {}", order.id, e);
+        }
+    }
+
+    let _ = state.market_events.send(MarketEvent::MarketResolved {
+        market_id: market_id.to_string(),
+        cancelled_order_ids: closed.order_ids(),
+    });
+    Ok(())
+}
+
+async fn archive_cancelled_order(
+    state: &Shared,
+    market: &Market,
+    share: ShareType,
+    order: &OrderEntry,
+    closed_at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    if close_open_order(&state.db_pool, order.id, OrderStatus::Cancelled).await? {
+        return Ok(());
+    }
+    // placed before orders were recorded on entry
+    let user = get_user_by_solana_address(&state.db_pool, &order.user_address).await?;
+    create_close_order(
+        &state.db_pool,
+        order.id,
+        market.id,
+        user.id,
+        share,
+        order.price.to_f64().unwrap_or_default(),
+        order.original_qty.to_f64().unwrap_or_default(),
+        order.filled_qty.to_f64().unwrap_or_default(),
+        OrderStatus::Cancelled,
+        closed_at,
+    )
+    .await?;
+    Ok(())
+}
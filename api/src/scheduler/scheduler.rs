@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use db::{
+    models::close_order::OrderStatus,
+    queries::market::{close_market, list_markets_due_for_close},
+};
+use tokio::sync::oneshot;
+
+use crate::{
+    engine::engine::{ClosedBook, EngineMsg},
+    models::events::MarketEvent,
+    state::state::Shared,
+    utils::order_log::record_closed,
+};
+
+const CLOSE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Moves open markets to closed once their close time has passed.
+pub async fn run_market_scheduler(state: Shared) {
+    let mut interval = tokio::time::interval(CLOSE_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = close_due_markets(&state).await {
+            println!("Market scheduler error: {}", e);
+        }
+    }
+}
+
+async fn close_due_markets(state: &Shared) -> anyhow::Result<()> {
+    for market in list_markets_due_for_close(&state.db_pool, Utc::now()).await? {
+        if !close_market(&state.db_pool, &market.market_id).await? {
+            continue;
+        }
+        let market_id = market.market_id.parse::<u64>()?;
+        let cancelled_order_ids = match halt_engine(state, market_id).await {
+            Some(closed) => {
+                // kept for the order book endpoint and reused at resolution
+                state
+                    .final_snapshots
+                    .write()
+                    .await
+                    .insert(market_id, closed.snapshot.clone());
+                closed.order_ids()
+            }
+            None => Vec::new(),
+        };
+        record_closed(state, &cancelled_order_ids, OrderStatus::Expired).await;
+        println!(
+            "Closed market {}, cancelled {} resting orders",
+            market.market_id,
+            cancelled_order_ids.len()
+        );
+        // nobody listening is fine
+        let _ = state.market_events.send(MarketEvent::MarketClosed {
+            market_id: market.market_id,
+            cancelled_order_ids,
+        });
+    }
+    Ok(())
+}
+
+/// Stops a market's engine from accepting orders and cancels everything
+/// resting on it, releasing the balances reserved for those orders. `None`
+/// if the market has no running engine.
+pub async fn halt_engine(state: &Shared, market_id: u64) -> Option<ClosedBook> {
+    let tx = state.markets.read().await.get(&market_id).cloned()?;
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(EngineMsg::Close { resp: resp_tx }).await.ok()?;
+    let closed = resp_rx.await.ok()?;
+    let mut balances = state.balances.lock().await;
+    for order in closed.yes.iter().chain(closed.no.iter()) {
+        balances.release(order.id);
+    }
+    Some(closed)
+}
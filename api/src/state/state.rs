@@ -1,13 +1,18 @@
 use std::{collections::HashMap, sync::Arc};
 
 use anchor_client_sdk::PredixSdk;
+use matching::types::MarketSnapshot;
 use aws_sdk_s3::Client;
 
 use solana_client::nonblocking::rpc_client::RpcClient;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{Mutex, RwLock, broadcast, mpsc};
 
 
-use crate::engine::engine::EngineMsg;
+use crate::{
+    balances::balances::BalanceCache,
+    engine::{engine::EngineMsg, index::OpenOrderIndex},
+    models::events::MarketEvent,
+};
 
 pub struct AppState {
     pub markets: RwLock<HashMap<u64, mpsc::Sender<EngineMsg>>>,
@@ -15,6 +20,12 @@ pub struct AppState {
     pub predix_sdk: Arc<PredixSdk>,
     pub s3: Arc<Client>,
     pub db_pool: Arc<sqlx::PgPool>,
+    pub balances: Mutex<BalanceCache>,
+    /// Resting orders per user across all engines, kept current by the engines.
+    pub open_orders: Arc<RwLock<OpenOrderIndex>>,
+    pub market_events: broadcast::Sender<MarketEvent>,
+    /// Last book of every market whose engine was retired on resolution.
+    pub final_snapshots: RwLock<HashMap<u64, MarketSnapshot>>,
 }
 
 pub type Shared = Arc<AppState>;
@@ -0,0 +1 @@
+pub mod stats;
\ No newline at end of file
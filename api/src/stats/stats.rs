@@ -0,0 +1,74 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::Utc;
+use db::{
+    models::market_stats::MarketStats,
+    queries::{
+        market_event::MARKET_ACTIVITY_CHANNEL,
+        market_stats::{list_market_stats, set_book_top, set_open_interest},
+    },
+};
+use sqlx::postgres::PgListener;
+
+use crate::{
+    state::state::Shared,
+    utils::{market::best_prices, order_log::to_f64},
+};
+
+/// Copies the top of the market's books into its stats. Nothing to do if its
+/// engine isn't running.
+pub async fn refresh_book_stats(state: &Shared, market_id: u64) {
+    let Some((yes, no)) = best_prices(state, market_id).await else {
+        return;
+    };
+    let yes = (yes.best_bid.map(to_f64), yes.best_ask.map(to_f64));
+    let no = (no.best_bid.map(to_f64), no.best_ask.map(to_f64));
+    if let Err(e) = set_book_top(&state.db_pool, &market_id.to_string(), yes, no).await {
+        println!("Failed to update book stats of market {}: {}", market_id, e);
+    }
+}
+
+/// Re-reads the share supplies from the market account.
+pub async fn refresh_open_interest(state: &Shared, market_id: &str) -> anyhow::Result<()> {
+    let chain = state.predix_sdk.fetch_market(market_id.parse::<u64>()?).await?;
+    set_open_interest(
+        &state.db_pool,
+        market_id,
+        chain.yes_total as i64,
+        chain.no_total as i64,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Stats of the given markets keyed by market id.
+pub async fn stats_by_market(
+    state: &Shared,
+    market_ids: &[String],
+) -> Result<HashMap<String, MarketStats>, sqlx::Error> {
+    let stats = list_market_stats(&state.db_pool, market_ids, Utc::now()).await?;
+    Ok(stats.into_iter().map(|s| (s.market_id.clone(), s)).collect())
+}
+
+/// Listens for share supply changes published by the event listener and
+/// refreshes the affected markets' open interest.
+pub async fn run_stats_listener(state: Shared) {
+    loop {
+        if let Err(e) = listen_market_activity(&state).await {
+            println!("Stats listener error: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn listen_market_activity(state: &Shared) -> anyhow::Result<()> {
+    let mut listener = PgListener::connect_with(&state.db_pool).await?;
+    listener.listen(MARKET_ACTIVITY_CHANNEL).await?;
+    loop {
+        let notification = listener.recv().await?;
+        let market_id = notification.payload();
+        if let Err(e) = refresh_open_interest(state, market_id).await {
+            println!("Failed to refresh open interest of market {}: {}", market_id, e);
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::{str::FromStr, time::Duration};
+
+use anchor_client::solana_sdk::commitment_config::CommitmentConfig;
+use anchor_client_sdk::BuiltTx;
+use db::{
+    models::transaction::{TrackedTransaction, TxPurpose, TxStatus},
+    queries::transaction::{
+        create_transaction, increment_transaction_attempts, list_unsettled_transactions,
+        update_transaction_status,
+    },
+};
+use chrono::Utc;
+use solana_client::client_error::ClientError;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::TransactionError};
+
+use crate::state::state::Shared;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_ATTEMPTS: i32 = 5;
+
+/// Records a backend-built transaction so its confirmation can be followed.
+///
+/// `resubmittable` must only be set for transactions the backend signed
+/// completely; user co-signed transactions are tracked but never re-sent.
+/// The transaction is already on the wire, so a row that can't be written only
+/// costs monitoring: it is logged, and any nonce lease it held is handed back.
+pub async fn track_transaction(
+    state: &Shared,
+    tx: &BuiltTx,
+    purpose: TxPurpose,
+    market_id: Option<&str>,
+    user_address: Option<&str>,
+    resubmittable: bool,
+) {
+    let serialized_tx = resubmittable.then_some(tx.tx_base64.as_str());
+    if let Err(e) = create_transaction(
+        &state.db_pool,
+        &tx.signature.to_string(),
+        purpose,
+        market_id,
+        user_address,
+        serialized_tx,
+        tx.last_valid_block_height as i64,
+        tx.nonce_account.map(|a| a.to_string()).as_deref(),
+    )
+    .await
+    {
+        println!("Failed to record transaction {}: {}", tx.signature, e);
+        // untracked, nothing would ever advance the nonce and end the lease
+        if let (Some(pool), Some(account)) = (state.predix_sdk.nonce_pool(), tx.nonce_account) {
+            pool.release(&account);
+        }
+    }
+}
+
+/// Sends a transaction the backend signed completely, records it as pending
+/// and waits for it to land. A send or confirmation failure marks the row
+/// failed, so it is never lost.
+pub async fn submit_transaction(
+    state: &Shared,
+    tx: &BuiltTx,
+    purpose: TxPurpose,
+    market_id: Option<&str>,
+    user_address: Option<&str>,
+) -> anyhow::Result<()> {
+    let sent = state.predix_sdk.send(tx).await;
+    track_transaction(state, tx, purpose, market_id, user_address, true).await;
+    let result = match sent {
+        Ok(_) => state.predix_sdk.confirm(tx).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &result {
+        if let Err(db_err) = update_transaction_status(
+            &state.db_pool,
+            &tx.signature.to_string(),
+            TxStatus::Failed,
+            Some(e.to_string()),
+        )
+        .await
+        {
+            println!(
+                "Failed to mark transaction {} failed: {}",
+                tx.signature, db_err
+            );
+        }
+    }
+    result
+}
+
+/// Polls every unsettled transaction and moves it through
+/// pending -> confirmed -> finalized, or to failed/expired.
+pub async fn run_tx_tracker(state: Shared) {
+    if let Err(e) = restore_nonce_leases(&state).await {
+        println!("Failed to restore nonce leases: {}", e);
+    }
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = poll_transactions(&state).await {
+            println!("Transaction tracker error: {}", e);
+        }
+    }
+}
+
+/// Re-leases the nonce accounts of transactions still waiting on a user
+/// signature, so a restart does not hand the same nonce out twice.
+async fn restore_nonce_leases(state: &Shared) -> anyhow::Result<()> {
+    let Some(pool) = state.predix_sdk.nonce_pool() else {
+        return Ok(());
+    };
+    for tracked in list_unsettled_transactions(&state.db_pool).await? {
+        if let Some(account) = tracked_nonce_account(&tracked) {
+            if tracked.status == TxStatus::Pending && pool.contains(&account) {
+                pool.mark_leased(account);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn tracked_nonce_account(tracked: &TrackedTransaction) -> Option<Pubkey> {
+    tracked.nonce_account.as_deref()?.parse().ok()
+}
+
+async fn poll_transactions(state: &Shared) -> anyhow::Result<()> {
+    let txs = list_unsettled_transactions(&state.db_pool).await?;
+    if txs.is_empty() {
+        return Ok(());
+    }
+    let block_height = state.rpc_client.get_block_height().await?;
+
+    // getSignatureStatuses accepts at most 256 signatures per call
+    for chunk in txs.chunks(256) {
+        let signatures = chunk
+            .iter()
+            .map(|t| Signature::from_str(&t.signature))
+            .collect::<Result<Vec<_>, _>>()?;
+        let statuses = state
+            .rpc_client
+            .get_signature_statuses_with_history(&signatures)
+            .await?
+            .value;
+
+        for (tracked, status) in chunk.iter().zip(statuses) {
+            match status {
+                Some(status) => {
+                    // the nonce advanced with this transaction, the account is free again
+                    if let (Some(pool), Some(account)) =
+                        (state.predix_sdk.nonce_pool(), tracked_nonce_account(tracked))
+                    {
+                        pool.release(&account);
+                    }
+                    let next = landed_status(
+                        &tracked.status,
+                        status.err.as_ref(),
+                        status.satisfies_commitment(CommitmentConfig::finalized()),
+                        status.satisfies_commitment(CommitmentConfig::confirmed()),
+                    );
+                    if let Some((next, error)) = next {
+                        update_transaction_status(&state.db_pool, &tracked.signature, next, error)
+                            .await?;
+                    }
+                }
+                None => {
+                    if let Err(e) = handle_missing(state, tracked, block_height).await {
+                        println!(
+                            "Failed to handle missing transaction {}: {}",
+                            tracked.signature, e
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The status a transaction the cluster has seen moves to, with the error to
+/// store, or `None` when it stays where it is.
+fn landed_status(
+    current: &TxStatus,
+    err: Option<&TransactionError>,
+    finalized: bool,
+    confirmed: bool,
+) -> Option<(TxStatus, Option<String>)> {
+    if let Some(err) = err {
+        Some((TxStatus::Failed, Some(err.to_string())))
+    } else if finalized {
+        Some((TxStatus::Finalized, None))
+    } else if confirmed && *current == TxStatus::Pending {
+        Some((TxStatus::Confirmed, None))
+    } else {
+        None
+    }
+}
+
+/// The cluster has not seen the signature (yet). Re-send it while the
+/// blockhash is still valid, otherwise flag it as expired. Durable-nonce
+/// transactions instead expire when their lease runs out, at which point the
+/// nonce is advanced so a late signature can no longer land.
+async fn handle_missing(
+    state: &Shared,
+    tracked: &TrackedTransaction,
+    block_height: u64,
+) -> anyhow::Result<()> {
+    if let Some(account) = tracked_nonce_account(tracked) {
+        let lease = state.predix_sdk.config().nonce_lease;
+        let leased_for = (Utc::now() - tracked.created_at).to_std().unwrap_or_default();
+        if leased_for < lease {
+            return Ok(());
+        }
+        if let Err(e) = state.predix_sdk.advance_nonce(&account).await {
+            // still leased; the next poll tries again
+            println!(
+                "Failed to advance nonce {} of {}: {}",
+                account, tracked.signature, e
+            );
+            return Ok(());
+        }
+        update_transaction_status(
+            &state.db_pool,
+            &tracked.signature,
+            TxStatus::Expired,
+            Some("not signed before the durable nonce lease ran out".into()),
+        )
+        .await?;
+        return Ok(());
+    }
+    if block_height as i64 > tracked.last_valid_block_height {
+        update_transaction_status(
+            &state.db_pool,
+            &tracked.signature,
+            TxStatus::Expired,
+            Some("blockhash expired before the transaction landed".into()),
+        )
+        .await?;
+        return Ok(());
+    }
+    let Some(serialized_tx) = &tracked.serialized_tx else {
+        // waiting on the user's wallet signature
+        return Ok(());
+    };
+    if tracked.attempts >= MAX_ATTEMPTS {
+        return Ok(());
+    }
+    match state.predix_sdk.rebroadcast(serialized_tx).await {
+        Ok(_) => increment_transaction_attempts(&state.db_pool, &tracked.signature).await?,
+        Err(e) if is_definitive(&e) => {
+            update_transaction_status(
+                &state.db_pool,
+                &tracked.signature,
+                TxStatus::Failed,
+                Some(e.to_string()),
+            )
+            .await?
+        }
+        Err(e) => {
+            // RPC hiccup or stale blockhash on the node: stays pending and is
+            // retried on the next poll
+            println!("Failed to rebroadcast {}: {}", tracked.signature, e);
+            increment_transaction_attempts(&state.db_pool, &tracked.signature).await?
+        }
+    }
+    Ok(())
+}
+
+/// Whether a rebroadcast error means the transaction can never land: it
+/// failed simulation or an instruction, or the signature was already
+/// processed. Transport errors and an unknown blockhash are transient.
+fn is_definitive(e: &anyhow::Error) -> bool {
+    let Some(tx_err) = e
+        .downcast_ref::<ClientError>()
+        .and_then(|e| e.get_transaction_error())
+    else {
+        return false;
+    };
+    !matches!(tx_err, TransactionError::BlockhashNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn landed_transactions_move_forward_only() {
+        assert_eq!(
+            landed_status(&TxStatus::Pending, None, false, true),
+            Some((TxStatus::Confirmed, None))
+        );
+        assert_eq!(landed_status(&TxStatus::Confirmed, None, false, true), None);
+        assert_eq!(
+            landed_status(&TxStatus::Confirmed, None, true, true),
+            Some((TxStatus::Finalized, None))
+        );
+        assert_eq!(landed_status(&TxStatus::Pending, None, false, false), None);
+    }
+
+    #[test]
+    fn a_landed_error_fails_the_transaction() {
+        let err = TransactionError::InsufficientFundsForFee;
+        assert_eq!(
+            landed_status(&TxStatus::Pending, Some(&err), true, true),
+            Some((TxStatus::Failed, Some(err.to_string())))
+        );
+    }
+
+    #[test]
+    fn only_transaction_errors_other_than_a_stale_blockhash_are_definitive() {
+        let failed = ClientError::from(TransactionError::InsufficientFundsForFee);
+        assert!(is_definitive(&failed.into()));
+        let stale = ClientError::from(TransactionError::BlockhashNotFound);
+        assert!(!is_definitive(&stale.into()));
+        assert!(!is_definitive(&anyhow::anyhow!("connection reset")));
+    }
+}
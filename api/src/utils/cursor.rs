@@ -0,0 +1,63 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Encodes a (timestamp, id) keyset position as an opaque, URL-safe cursor.
+pub fn encode_cursor(created_at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}_{}", created_at.timestamp_micros(), id)
+}
+
+pub fn decode_cursor(cursor: &str) -> Result<(DateTime<Utc>, Uuid), (StatusCode, String)> {
+    let invalid = || (StatusCode::BAD_REQUEST, "Invalid cursor".to_string());
+    let (micros, id) = cursor.split_once('_').ok_or_else(invalid)?;
+    let created_at = micros
+        .parse::<i64>()
+        .ok()
+        .and_then(DateTime::from_timestamp_micros)
+        .ok_or_else(invalid)?;
+    let id = id.parse::<Uuid>().map_err(|_| invalid())?;
+    Ok((created_at, id))
+}
+
+/// Encodes a (total volume, id) keyset position.
+pub fn encode_volume_cursor(volume: f64, id: Uuid) -> String {
+    format!("{}_{}", volume, id)
+}
+
+pub fn decode_volume_cursor(cursor: &str) -> Result<(f64, Uuid), (StatusCode, String)> {
+    let invalid = || (StatusCode::BAD_REQUEST, "Invalid cursor".to_string());
+    let (volume, id) = cursor.split_once('_').ok_or_else(invalid)?;
+    let volume = volume.parse::<f64>().map_err(|_| invalid())?;
+    let id = id.parse::<Uuid>().map_err(|_| invalid())?;
+    Ok((volume, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips_to_the_microsecond() {
+        let created_at = DateTime::from_timestamp_micros(1_766_000_000_123_456).unwrap();
+        let id = Uuid::new_v4();
+        let cursor = encode_cursor(created_at, id);
+        assert_eq!(decode_cursor(&cursor), Ok((created_at, id)));
+    }
+
+    #[test]
+    fn malformed_cursors_are_a_bad_request() {
+        let bad_micros = format!("abc_{}", Uuid::new_v4());
+        for cursor in ["", "123", bad_micros.as_str(), "123_not-a-uuid"] {
+            let (status, _) = decode_cursor(cursor).unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn volume_cursor_round_trips() {
+        let id = Uuid::new_v4();
+        let cursor = encode_volume_cursor(1234.5, id);
+        assert_eq!(decode_volume_cursor(&cursor), Ok((1234.5, id)));
+        assert!(decode_volume_cursor(&format!("many_{id}")).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use axum::http::StatusCode;
+use db::models::{close_order::OrderStatus, market::Market};
+use matching::types::{BestPrices, CancelFilter};
+use solana_sdk::pubkey::Pubkey;
+use sqlx::PgPool;
+use std::str::FromStr;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+use crate::{
+    engine::engine::{EngineMsg, run_market_engine},
+    state::state::Shared,
+    stats::stats::refresh_book_stats,
+    utils::order_log::record_closed,
+};
+
+/// Loads a market row, mapping a missing row to 404.
+pub async fn fetch_market(pool: &PgPool, market_id: &str) -> Result<Market, (StatusCode, String)> {
+    db::queries::market::get_market_by_id(pool, market_id.to_string())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "market not found".into()),
+            e => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch market: {}", e),
+            ),
+        })
+}
+
+pub fn market_collateral_mint(market: &Market) -> Result<Pubkey, (StatusCode, String)> {
+    Pubkey::from_str(&market.collateral_mint).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Invalid collateral mint stored for market: {}", e),
+        )
+    })
+}
+
+/// Top of the market's (yes, no) books, or `None` if its engine isn't running.
+pub async fn best_prices(state: &Shared, market_id: u64) -> Option<(BestPrices, BestPrices)> {
+    let tx = state.markets.read().await.get(&market_id).cloned()?;
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(EngineMsg::BestPrices { resp: resp_tx }).await.ok()?;
+    resp_rx.await.ok()
+}
+
+/// The market's engine, started on first use.
+pub async fn engine_for(state: &Shared, market_id: u64) -> mpsc::Sender<EngineMsg> {
+    let mut markets = state.markets.write().await;
+    if let Some(tx) = markets.get(&market_id) {
+        return tx.clone();
+    }
+    let (tx, rx) = mpsc::channel::<EngineMsg>(100);
+    tokio::spawn(run_market_engine(market_id, state.open_orders.clone(), rx));
+    markets.insert(market_id, tx.clone());
+    tx
+}
+
+/// Cancels every resting order of the market matching `filter` and releases
+/// what they reserved. Returns the cancelled order ids.
+pub async fn cancel_matching_orders(
+    state: &Shared,
+    market_id: u64,
+    filter: CancelFilter,
+) -> Result<Vec<Uuid>, (StatusCode, String)> {
+    // no engine means nothing rests
+    let Some(tx) = state.markets.read().await.get(&market_id).cloned() else {
+        return Ok(Vec::new());
+    };
+    let (resp_tx, resp_rx) = oneshot::channel();
+    tx.send(EngineMsg::CancelAll {
+        filter,
+        resp: resp_tx,
+    })
+    .await
+    .map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "engine send failed".into(),
+        )
+    })?;
+    let removed = resp_rx
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "engine dropped".into()))?;
+    let ids = removed.iter().map(|o| o.id).collect::<Vec<_>>();
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+    {
+        let mut balances = state.balances.lock().await;
+        for id in &ids {
+            balances.release(*id);
+        }
+    }
+    record_closed(state, &ids, OrderStatus::Cancelled).await;
+    refresh_book_stats(state, market_id).await;
+    Ok(ids)
+}
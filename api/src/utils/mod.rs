@@ -1,2 +1,6 @@
+pub mod cursor;
+pub mod market;
+pub mod order_log;
 pub mod solana;
-pub mod s3;
\ No newline at end of file
+pub mod s3;
+pub mod settlement;
\ No newline at end of file
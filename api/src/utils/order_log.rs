@@ -0,0 +1,138 @@
+use db::{
+    models::{
+        close_order::{OrderSide, OrderStatus, ShareType as DbShareType},
+        market::Market,
+    },
+    queries::{
+        order::{amend_open_order, close_open_order, create_order, record_order_fill},
+        market_stats::record_trade_stats,
+        trade::create_trade,
+        user::get_user_by_solana_address,
+    },
+};
+use matching::types::{OrderEntry, Side, Trade};
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use uuid::Uuid;
+
+use crate::{models::orders::ShareType, state::state::Shared};
+
+// Persistence outside the matching engine is best effort. The engine is the
+// source of truth for resting orders and these writes are the audit trail, so
+// a database error is logged instead of failing the caller.
+
+/// Records an order accepted by the API before it reaches the engine.
+pub async fn record_new_order(state: &Shared, market: &Market, order: &OrderEntry, share: ShareType) {
+    let user_id = get_user_by_solana_address(&state.db_pool, &order.user_address)
+        .await
+        .ok()
+        .map(|u| u.id);
+    if let Err(e) = create_order(
+        &state.db_pool,
+        order.id,
+        market.id,
+        user_id,
+        &order.user_address,
+        db_share_type(share),
+        db_side(&order.side),
+        to_f64(order.price),
+        to_f64(order.qty),
+    )
+    .await
+    {
+        println!("Failed to record order {}: {}", order.id, e);
+    }
+}
+
+/// Records each trade as a fill of both its maker order and the taker order.
+pub async fn record_fills(state: &Shared, taker_order_id: Uuid, trades: &[Trade]) {
+    for t in trades {
+        for order_id in [t.maker_order_id, taker_order_id] {
+            if let Err(e) =
+                record_order_fill(&state.db_pool, order_id, to_f64(t.quantity), to_f64(t.price))
+                    .await
+            {
+                println!("Failed to record fill of order {}: {}", order_id, e);
+            }
+        }
+    }
+}
+
+/// Records the settled trades of one taker order.
+pub async fn record_trades(
+    state: &Shared,
+    market_id: &str,
+    share: ShareType,
+    taker_side: &Side,
+    taker_order_id: Uuid,
+    trades: &[Trade],
+    signature: &str,
+) {
+    for t in trades {
+        if let Err(e) = create_trade(
+            &state.db_pool,
+            market_id,
+            db_share_type(share),
+            db_side(taker_side),
+            t.maker_order_id,
+            taker_order_id,
+            &t.buyer_address,
+            &t.seller_address,
+            to_f64(t.price),
+            to_f64(t.quantity),
+            Some(signature),
+        )
+        .await
+        {
+            println!("Failed to record trade of order {}: {}", taker_order_id, e);
+        }
+        if let Err(e) = record_trade_stats(
+            &state.db_pool,
+            market_id,
+            db_share_type(share),
+            to_f64(t.price),
+            to_f64(t.quantity),
+        )
+        .await
+        {
+            println!("Failed to update stats of market {}: {}", market_id, e);
+        }
+    }
+}
+
+/// Records the new price and open quantity of an amended order, before any
+/// fills the amend itself produced.
+pub async fn record_amended(state: &Shared, order_id: Uuid, price: Decimal, open_qty: Decimal) {
+    if let Err(e) =
+        amend_open_order(&state.db_pool, order_id, to_f64(price), to_f64(open_qty)).await
+    {
+        println!("Failed to record amend of order {}: {}", order_id, e);
+    }
+}
+
+/// Records resting orders that left the book without filling (cancelled,
+/// rejected or expired).
+pub async fn record_closed(state: &Shared, order_ids: &[Uuid], status: OrderStatus) {
+    for order_id in order_ids {
+        if let Err(e) = close_open_order(&state.db_pool, *order_id, status.clone()).await {
+            println!("Failed to record order {} as {:?}: {}", order_id, status, e);
+        }
+    }
+}
+
+fn db_share_type(share: ShareType) -> DbShareType {
+    match share {
+        ShareType::Yes => DbShareType::Yes,
+        ShareType::No => DbShareType::No,
+    }
+}
+
+fn db_side(side: &Side) -> OrderSide {
+    match side {
+        Side::Bid => OrderSide::Bid,
+        Side::Ask => OrderSide::Ask,
+    }
+}
+
+pub fn to_f64(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or_default()
+}
@@ -0,0 +1,367 @@
+use anchor_client_sdk::{
+    predix_program::types::TradeSide,
+    utils::{get_match_fills, get_remaining_accounts},
+};
+use axum::http::StatusCode;
+use db::models::{close_order::OrderStatus, transaction::TxPurpose};
+use matching::types::{MarketOrder, OrderEntry, Side, Trade};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+use crate::{
+    engine::engine::EngineMsg,
+    models::orders::ShareType,
+    state::state::Shared,
+    tracker::tracker::submit_transaction,
+    utils::{
+        order_log::{record_closed, record_fills, record_trades},
+        solana::{TradeMints, find_insufficient_allowances, maker_allowances},
+    },
+};
+
+/// The trades one taker order matched, settled alongside any others of the
+/// same market.
+#[derive(Debug, Clone)]
+pub struct TakerFills {
+    pub order_id: Uuid,
+    pub share: ShareType,
+    pub side: Side,
+    pub trades: Vec<Trade>,
+}
+
+/// Verifies the makers of every fill. Makers that can't pay are pulled from
+/// the book and the quantity matched against them is handed back to the
+/// engine: `retry` builds the taker to re-match from the rejected trades and
+/// the current remainder. Each round removes at least one maker order, so
+/// this terminates. Returns the verified trades and the taker's remainder.
+pub async fn verify_makers(
+    state: &Shared,
+    engine: &mpsc::Sender<EngineMsg>,
+    market_pda: &Pubkey,
+    mints: &TradeMints,
+    share: ShareType,
+    side: &Side,
+    mut pending: Vec<Trade>,
+    mut rem: Decimal,
+    retry: impl Fn(&[Trade], Decimal) -> (OrderEntry, Option<MarketOrder>),
+) -> Result<(Vec<Trade>, Decimal), (StatusCode, String)> {
+    let mut trades: Vec<Trade> = Vec::new();
+    while !pending.is_empty() {
+        let checks = maker_allowances(&pending, side, mints).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Verification error: {}", e),
+            )
+        })?;
+        let failures = find_insufficient_allowances(&state.rpc_client, market_pda, &checks)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Verification error: {}", e),
+                )
+            })?;
+        if failures.is_empty() {
+            trades.append(&mut pending);
+            break;
+        }
+        let bad_makers = failures
+            .iter()
+            .map(|(check, reason)| {
+                println!("Rejecting maker {}: {}", check.owner, reason);
+                check.owner.to_string()
+            })
+            .collect::<HashSet<String>>();
+        let (rejected, good): (Vec<Trade>, Vec<Trade>) =
+            pending.into_iter().partition(|t| match side {
+                Side::Bid => bad_makers.contains(&t.seller_address),
+                Side::Ask => bad_makers.contains(&t.buyer_address),
+            });
+        trades.extend(good);
+        let rejected_ids = rejected
+            .iter()
+            .map(|t| t.maker_order_id)
+            .collect::<Vec<_>>();
+        {
+            let mut balances = state.balances.lock().await;
+            for id in &rejected_ids {
+                balances.release(*id);
+            }
+        }
+        record_closed(state, &rejected_ids, OrderStatus::Cancelled).await;
+
+        let (taker, market) = retry(&rejected, rem);
+        let (resp_tx, resp_rx) = oneshot::channel();
+        engine
+            .send(EngineMsg::RejectMakers {
+                side: side.clone(),
+                share,
+                taker,
+                market,
+                maker_order_ids: rejected_ids,
+                resp: resp_tx,
+            })
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "engine send failed".into(),
+                )
+            })?;
+        let (_, next, retry_rem) = resp_rx
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "engine dropped".into()))?
+            .map_err(|reason| (StatusCode::CONFLICT, reason))?;
+        pending = next;
+        // a limit taker's remainder already folds in what rests; a market
+        // order never rests, so whatever the retry left unfilled adds up
+        rem = match market {
+            Some(_) => rem + retry_rem,
+            None => retry_rem,
+        };
+    }
+    Ok((trades, rem))
+}
+
+/// Takes a taker order whose fills can't be verified off the book: whatever
+/// of it rests is cancelled in the engine, its reservation is released and
+/// its row closed.
+pub async fn withdraw_taker(
+    state: &Shared,
+    engine: &mpsc::Sender<EngineMsg>,
+    share: ShareType,
+    side: &Side,
+    price: Decimal,
+    order_id: Uuid,
+    user_address: &str,
+) {
+    let (resp_tx, resp_rx) = oneshot::channel();
+    let sent = engine
+        .send(EngineMsg::CloseOrder {
+            side: side.clone(),
+            share,
+            price,
+            order_id,
+            user_address: user_address.to_string(),
+            resp: resp_tx,
+        })
+        .await;
+    // a fully matched order doesn't rest, so "not found" is fine here
+    if sent.is_err() || resp_rx.await.is_err() {
+        println!("Failed to cancel order {} in the engine", order_id);
+    }
+    state.balances.lock().await.release(order_id);
+    record_closed(state, &[order_id], OrderStatus::Cancelled).await;
+}
+
+/// Settles the fills of one or more takers of a market and records them,
+/// in as many transactions as the fills need (see [`settlement_batches`]).
+/// Returns the signatures in order; none if nothing matched. A failed
+/// transaction stops the rest, the ones before it stay settled. Reservations
+/// are released as each transaction lands, so fills that never settle keep
+/// theirs until their orders close.
+pub async fn settle_fills(
+    state: &Shared,
+    market_id: &str,
+    mints: &TradeMints,
+    taker_address: &str,
+    fills: &[TakerFills],
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let market_num = market_id
+        .parse::<u64>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid market id: {}", e)))?;
+    let program_id = state.predix_sdk.program_id();
+    let mut signatures = Vec::new();
+    for batch in settlement_batches(fills) {
+        let mut match_fills = Vec::new();
+        let mut remaining_accounts = Vec::new();
+        for taker in &batch {
+            let trade_side = match taker.share {
+                ShareType::Yes => TradeSide::Yes,
+                ShareType::No => TradeSide::No,
+            };
+            match_fills.extend(
+                get_match_fills(
+                    &taker.trades,
+                    trade_side,
+                    mints.share_decimals,
+                    mints.collateral_decimals,
+                )
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Failed to build match fills: {}", e),
+                    )
+                })?,
+            );
+            remaining_accounts.extend(get_remaining_accounts(
+                &taker.trades,
+                trade_side,
+                market_num,
+                &program_id,
+                &mints.collateral_mint,
+            ));
+        }
+        let built_tx = state
+            .predix_sdk
+            .place_order(market_num, match_fills, remaining_accounts)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to place order on chain: {}", e),
+                )
+            })?;
+        submit_transaction(
+            state,
+            &built_tx,
+            TxPurpose::PlaceOrder,
+            Some(market_id),
+            Some(taker_address),
+        )
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to settle fills on chain: {}", e),
+            )
+        })?;
+        // settled quantity no longer rests, so it no longer needs a reservation
+        {
+            let mut balances = state.balances.lock().await;
+            for taker in &batch {
+                let mut taker_filled = Decimal::ZERO;
+                for t in &taker.trades {
+                    balances.release_filled(t.maker_order_id, t.quantity);
+                    taker_filled += t.quantity;
+                }
+                balances.release_filled(taker.order_id, taker_filled);
+            }
+        }
+        let signature = built_tx.signature.to_string();
+        for taker in &batch {
+            record_fills(state, taker.order_id, &taker.trades).await;
+            record_trades(
+                state,
+                market_id,
+                taker.share,
+                &taker.side,
+                taker.order_id,
+                &taker.trades,
+                &signature,
+            )
+            .await;
+        }
+        signatures.push(signature);
+    }
+    Ok(signatures)
+}
+
+/// Most trades settled by one transaction. Each adds a fill argument and six
+/// accounts, three of them the maker's own as the taker's are shared, so five
+/// keep a transaction well within the 1232 byte packet size.
+const MAX_FILLS_PER_TX: usize = 5;
+
+/// Splits the fills into the transactions that settle them, in order, at most
+/// [`MAX_FILLS_PER_TX`] trades each. A taker with more trades than fit spans
+/// several transactions.
+fn settlement_batches(fills: &[TakerFills]) -> Vec<Vec<TakerFills>> {
+    let mut batches: Vec<Vec<TakerFills>> = Vec::new();
+    let mut room = 0;
+    for taker in fills {
+        let mut trades = taker.trades.as_slice();
+        while !trades.is_empty() {
+            if room == 0 {
+                batches.push(Vec::new());
+                room = MAX_FILLS_PER_TX;
+            }
+            let (now, later) = trades.split_at(room.min(trades.len()));
+            room -= now.len();
+            batches
+                .last_mut()
+                .expect("a batch was just opened")
+                .push(TakerFills {
+                    order_id: taker.order_id,
+                    share: taker.share,
+                    side: taker.side.clone(),
+                    trades: now.to_vec(),
+                });
+            trades = later;
+        }
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taker(share: ShareType, trades: usize) -> TakerFills {
+        TakerFills {
+            order_id: Uuid::new_v4(),
+            share,
+            side: Side::Bid,
+            trades: (0..trades)
+                .map(|_| Trade {
+                    market_id: 1,
+                    maker_order_id: Uuid::new_v4(),
+                    buyer_address: "buyer".into(),
+                    seller_address: "seller".into(),
+                    price: Decimal::new(5, 1),
+                    quantity: Decimal::ONE,
+                })
+                .collect(),
+        }
+    }
+
+    fn shape(batches: &[Vec<TakerFills>]) -> Vec<Vec<(Uuid, usize)>> {
+        batches
+            .iter()
+            .map(|b| b.iter().map(|t| (t.order_id, t.trades.len())).collect())
+            .collect()
+    }
+
+    #[test]
+    fn small_settlements_take_one_transaction() {
+        let (yes, no) = (taker(ShareType::Yes, 2), taker(ShareType::No, 3));
+        let batches = settlement_batches(&[yes.clone(), no.clone()]);
+        assert_eq!(
+            shape(&batches),
+            vec![vec![(yes.order_id, 2), (no.order_id, 3)]]
+        );
+    }
+
+    #[test]
+    fn large_settlements_split_across_transactions_in_order() {
+        let (first, second) = (taker(ShareType::Yes, 7), taker(ShareType::No, 4));
+        let batches = settlement_batches(&[first.clone(), second.clone()]);
+        assert_eq!(
+            shape(&batches),
+            vec![
+                vec![(first.order_id, 5)],
+                vec![(first.order_id, 2), (second.order_id, 3)],
+                vec![(second.order_id, 1)],
+            ]
+        );
+        let settled = batches
+            .iter()
+            .flatten()
+            .filter(|t| t.order_id == first.order_id)
+            .flat_map(|t| t.trades.iter().map(|trade| trade.maker_order_id))
+            .collect::<Vec<_>>();
+        let matched = first
+            .trades
+            .iter()
+            .map(|t| t.maker_order_id)
+            .collect::<Vec<_>>();
+        assert_eq!(settled, matched);
+    }
+
+    #[test]
+    fn takers_without_trades_settle_nothing() {
+        assert!(settlement_batches(&[taker(ShareType::Yes, 0)]).is_empty());
+    }
+}
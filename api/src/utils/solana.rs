@@ -1,41 +1,153 @@
-use std::str::FromStr;
+use std::collections::HashMap;
 
-use anchor_lang::{declare_program, prelude::Pubkey};
+use anchor_client_sdk::utils::to_base_units;
+use anchor_lang::prelude::Pubkey;
+use matching::types::{Side, Trade};
+use rust_decimal::{Decimal, RoundingStrategy};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::program_pack::Pack;
 use spl_associated_token_account::get_associated_token_address;
 use spl_token::state::Account;
 
-declare_program!(predix_program);
+/// Amount a wallet must have approved to the market PDA on one mint.
+#[derive(Debug, Clone)]
+pub struct AllowanceCheck {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+/// Mints involved in trading one outcome of a market.
+#[derive(Debug, Clone)]
+pub struct TradeMints {
+    pub collateral_mint: Pubkey,
+    pub collateral_decimals: u8,
+    pub share_mint: Pubkey,
+    pub share_decimals: u8,
+}
+
+/// Allowance needed to trade `qty` shares at `price` on `side`: collateral for
+/// the buyer (rounded up), outcome shares for the seller.
+pub fn required_allowance(
+    owner: Pubkey,
+    side: &Side,
+    price: Decimal,
+    qty: Decimal,
+    mints: &TradeMints,
+) -> Result<AllowanceCheck, anyhow::Error> {
+    let check = match side {
+        Side::Bid => AllowanceCheck {
+            owner,
+            mint: mints.collateral_mint,
+            amount: to_base_units(
+                price * qty,
+                mints.collateral_decimals,
+                RoundingStrategy::AwayFromZero,
+            )?,
+        },
+        Side::Ask => AllowanceCheck {
+            owner,
+            mint: mints.share_mint,
+            amount: to_base_units(qty, mints.share_decimals, RoundingStrategy::AwayFromZero)?,
+        },
+    };
+    Ok(check)
+}
+
+/// Aggregated allowance each maker needs to cover its side of `trades`,
+/// where `taker_side` is the side of the incoming order.
+pub fn maker_allowances(
+    trades: &[Trade],
+    taker_side: &Side,
+    mints: &TradeMints,
+) -> Result<Vec<AllowanceCheck>, anyhow::Error> {
+    let mut totals: HashMap<&String, Decimal> = HashMap::new();
+    for t in trades {
+        match taker_side {
+            // makers sold shares to the taker
+            Side::Bid => *totals.entry(&t.seller_address).or_default() += t.quantity,
+            // makers paid collateral to the taker
+            Side::Ask => *totals.entry(&t.buyer_address).or_default() += t.price * t.quantity,
+        }
+    }
+    let (mint, decimals) = match taker_side {
+        Side::Bid => (mints.share_mint, mints.share_decimals),
+        Side::Ask => (mints.collateral_mint, mints.collateral_decimals),
+    };
+    totals
+        .into_iter()
+        .map(|(maker, total)| {
+            Ok(AllowanceCheck {
+                owner: maker.parse::<Pubkey>()?,
+                mint,
+                amount: to_base_units(total, decimals, RoundingStrategy::AwayFromZero)?,
+            })
+        })
+        .collect()
+}
+
+/// Checks the owners' ATAs for `mint`: the ATA must exist, have `delegate` as
+/// its delegate, and both `delegated_amount` and the balance must cover
+/// `amount`. Returns the failing checks together with the reason.
+pub async fn find_insufficient_allowances(
+    rpc: &RpcClient,
+    delegate: &Pubkey,
+    checks: &[AllowanceCheck],
+) -> Result<Vec<(AllowanceCheck, String)>, anyhow::Error> {
+    let shortfalls = allowance_shortfalls(rpc, delegate, checks).await?;
+    Ok(checks
+        .iter()
+        .zip(shortfalls)
+        .filter_map(|(check, reason)| Some((check.clone(), reason?)))
+        .collect())
+}
 
-pub async fn verify_delegation(
+/// Like [`find_insufficient_allowances`], but answers every check in order:
+/// the reason it fails, or `None` if it passes.
+pub async fn allowance_shortfalls(
     rpc: &RpcClient,
-    wallet_address: &str,
-    collateral_mint: &str,
-) -> Result<(), anyhow::Error> {
-    let program_id = predix_program::ID;
-    let wallet_address = Pubkey::from_str(wallet_address).expect("Invalid wallet address");
-    let collateral_mint =
-        Pubkey::from_str(collateral_mint).expect("Invalid collateral mint address");
-
-    let collateral_ata = get_associated_token_address(&wallet_address, &collateral_mint);
-
-    let account_data = rpc
-        .get_account_data(&collateral_ata)
-        .await
-        .expect("Failed to get account data");
-    let account_data = Account::unpack(&account_data).expect("Failed to unpack account data");
-    println!("Collateral ATA account data: {:?}", account_data);
-    dbg!("Program ID: {:?}", program_id);
-    dbg!("Wallet Address: {:?}", wallet_address);
-    dbg!("Collateral Mint: {:?}", collateral_mint);
-    dbg!("Collateral ATA: {:?}", collateral_ata);
-    dbg!("Account Data: {:?}", account_data);
-    dbg!("Delegate: {:?}", account_data.delegate);
-
-    Ok(())
+    delegate: &Pubkey,
+    checks: &[AllowanceCheck],
+) -> Result<Vec<Option<String>>, anyhow::Error> {
+    let mut shortfalls = Vec::with_capacity(checks.len());
+    // getMultipleAccounts accepts at most 100 keys per call
+    for chunk in checks.chunks(100) {
+        let atas = chunk
+            .iter()
+            .map(|c| get_associated_token_address(&c.owner, &c.mint))
+            .collect::<Vec<_>>();
+        let accounts = rpc.get_multiple_accounts(&atas).await?;
+        for (check, account) in chunk.iter().zip(accounts) {
+            let reason = match account {
+                None => Some("token account does not exist".to_string()),
+                Some(account) => match Account::unpack(&account.data) {
+                    Err(e) => Some(format!("invalid token account: {}", e)),
+                    Ok(token) => {
+                        let token_delegate: Option<Pubkey> = token.delegate.into();
+                        if token_delegate != Some(*delegate) {
+                            Some("market is not the delegate".to_string())
+                        } else if token.delegated_amount < check.amount {
+                            Some(format!(
+                                "delegated amount {} is below required {}",
+                                token.delegated_amount, check.amount
+                            ))
+                        } else if token.amount < check.amount {
+                            Some(format!(
+                                "balance {} is below required {}",
+                                token.amount, check.amount
+                            ))
+                        } else {
+                            None
+                        }
+                    }
+                },
+            };
+            shortfalls.push(reason);
+        }
+    }
+    Ok(shortfalls)
 }
 
-pub fn derive_market_pda(market_id: u64) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"market", &market_id.to_le_bytes().as_ref()], &predix_program::ID)
+pub fn derive_market_pda(market_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"market", &market_id.to_le_bytes().as_ref()], program_id)
 }
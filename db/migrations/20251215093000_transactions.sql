@@ -0,0 +1,24 @@
+CREATE TYPE tx_status AS ENUM ('pending', 'confirmed', 'finalized', 'failed', 'expired');
+CREATE TYPE tx_purpose AS ENUM ('create_market', 'place_order', 'split', 'merge', 'set_winner', 'delegate_approval');
+
+CREATE TABLE transactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+
+    signature TEXT UNIQUE NOT NULL,
+    purpose tx_purpose NOT NULL,
+    market_id TEXT,
+    user_address TEXT,
+    status tx_status NOT NULL DEFAULT 'pending',
+    error TEXT,
+
+    -- only set for transactions the backend signed completely and may resubmit
+    serialized_tx TEXT,
+    last_valid_block_height BIGINT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_transactions_status ON transactions (status);
+CREATE INDEX idx_transactions_market_id ON transactions (market_id);
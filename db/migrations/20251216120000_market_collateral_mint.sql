@@ -0,0 +1,8 @@
+-- Markets created before this migration all settled against the devnet USDC mint.
+ALTER TABLE markets
+    ADD COLUMN collateral_mint TEXT NOT NULL DEFAULT '4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU',
+    ADD COLUMN collateral_decimals SMALLINT NOT NULL DEFAULT 6;
+
+ALTER TABLE markets
+    ALTER COLUMN collateral_mint DROP DEFAULT,
+    ALTER COLUMN collateral_decimals DROP DEFAULT;
@@ -0,0 +1,4 @@
+-- YES/NO mints of existing markets were created with 6 decimals.
+ALTER TABLE markets ADD COLUMN share_decimals SMALLINT NOT NULL DEFAULT 6;
+
+ALTER TABLE markets ALTER COLUMN share_decimals DROP DEFAULT;
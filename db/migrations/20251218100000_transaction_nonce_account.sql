@@ -0,0 +1,2 @@
+-- durable nonce account backing a user co-signed transaction, if any
+ALTER TABLE transactions ADD COLUMN nonce_account TEXT;
@@ -0,0 +1,19 @@
+-- differences between a market row and its on-chain account, found by the reconciliation job
+CREATE TABLE market_reconciliation_issues (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+
+    market_id TEXT NOT NULL,
+    field TEXT NOT NULL,
+    -- what the market row (or the market account) says vs. what the chain holds
+    expected TEXT NOT NULL,
+    actual TEXT NOT NULL,
+
+    detected_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    resolved_at TIMESTAMP WITH TIME ZONE
+);
+
+-- at most one open issue per market and field
+CREATE UNIQUE INDEX idx_reconciliation_open_issue
+    ON market_reconciliation_issues (market_id, field) WHERE resolved_at IS NULL;
+CREATE INDEX idx_reconciliation_market_id ON market_reconciliation_issues (market_id);
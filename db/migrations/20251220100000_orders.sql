@@ -0,0 +1,15 @@
+-- closed (filled or cancelled) orders; resting orders live in the matching engine
+CREATE TABLE IF NOT EXISTS orders (
+    id UUID PRIMARY KEY,
+    market_id UUID NOT NULL REFERENCES markets (id),
+    user_id UUID NOT NULL REFERENCES users (id),
+    type share_type NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    qty DOUBLE PRECISION NOT NULL,
+    filled_qty DOUBLE PRECISION NOT NULL DEFAULT 0,
+    status order_status NOT NULL,
+    closed_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_orders_user_id ON orders (user_id);
+CREATE INDEX IF NOT EXISTS idx_orders_market_id ON orders (market_id);
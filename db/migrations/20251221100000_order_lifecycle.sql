@@ -0,0 +1,18 @@
+-- orders are now recorded when placed and updated on every fill, cancel and expiry
+ALTER TYPE order_status ADD VALUE IF NOT EXISTS 'open' BEFORE 'partially_filled';
+ALTER TYPE order_status ADD VALUE IF NOT EXISTS 'expired';
+
+CREATE TYPE order_side AS ENUM ('bid', 'ask');
+
+-- not every wallet has a users row, so the address is what identifies the owner
+ALTER TABLE orders
+    ADD COLUMN user_address TEXT,
+    ADD COLUMN side order_side,
+    ADD COLUMN avg_fill_price DOUBLE PRECISION,
+    ADD COLUMN created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    ADD COLUMN updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    ALTER COLUMN user_id DROP NOT NULL,
+    ALTER COLUMN closed_at DROP NOT NULL;
+
+-- a user's order history, newest first
+CREATE INDEX idx_orders_user_created ON orders (user_address, created_at DESC, id DESC);
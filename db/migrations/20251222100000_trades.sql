@@ -0,0 +1,22 @@
+CREATE TABLE trades (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+
+    -- on-chain market id, as in markets.market_id
+    market_id TEXT NOT NULL,
+    outcome share_type NOT NULL,
+    taker_side order_side NOT NULL,
+    maker_order_id UUID NOT NULL,
+    taker_order_id UUID NOT NULL,
+    buyer_address TEXT NOT NULL,
+    seller_address TEXT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    qty DOUBLE PRECISION NOT NULL,
+    -- settlement transaction
+    signature TEXT,
+
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_trades_buyer ON trades (buyer_address, created_at DESC, id DESC);
+CREATE INDEX idx_trades_seller ON trades (seller_address, created_at DESC, id DESC);
+CREATE INDEX idx_trades_market_id ON trades (market_id, created_at);
@@ -0,0 +1,14 @@
+-- Periodic top-of-book samples; the mid-price candles are built from these.
+CREATE TABLE mid_price_samples (
+    -- on-chain market id, as in markets.market_id
+    market_id TEXT NOT NULL,
+    outcome share_type NOT NULL,
+    best_bid DOUBLE PRECISION NOT NULL,
+    best_ask DOUBLE PRECISION NOT NULL,
+    mid DOUBLE PRECISION NOT NULL,
+    sampled_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (market_id, outcome, sampled_at)
+);
+
+CREATE INDEX idx_trades_market_outcome ON trades (market_id, outcome, created_at);
@@ -0,0 +1,19 @@
+-- Running totals per market, updated as trades are recorded, the book moves
+-- and shares are minted or burned on chain. 24h figures are derived from
+-- trades when read.
+CREATE TABLE market_stats (
+    -- on-chain market id, as in markets.market_id
+    market_id TEXT PRIMARY KEY,
+    total_volume DOUBLE PRECISION NOT NULL DEFAULT 0,
+    trade_count BIGINT NOT NULL DEFAULT 0,
+    -- share supplies from the on-chain market account, in base units
+    yes_total BIGINT NOT NULL DEFAULT 0,
+    no_total BIGINT NOT NULL DEFAULT 0,
+    yes_last_price DOUBLE PRECISION,
+    no_last_price DOUBLE PRECISION,
+    yes_best_bid DOUBLE PRECISION,
+    yes_best_ask DOUBLE PRECISION,
+    no_best_bid DOUBLE PRECISION,
+    no_best_ask DOUBLE PRECISION,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,8 @@
+ALTER TABLE markets ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(description, '')), 'B')
+) STORED;
+
+CREATE INDEX idx_markets_search ON markets USING GIN (search_vector);
+CREATE INDEX idx_markets_created ON markets (created_at DESC, id DESC);
+CREATE INDEX idx_market_stats_volume ON market_stats (total_volume DESC);
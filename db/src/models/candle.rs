@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// One time bucket of a price series. The prices are `None` for buckets
+/// without any data; volume and trade count are always zero for mid prices.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct CandleRow {
+    pub bucket_start: DateTime<Utc>,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: Option<f64>,
+    pub volume: f64,
+    pub trade_count: i64,
+}
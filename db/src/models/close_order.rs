@@ -12,24 +12,55 @@ pub enum ShareType {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Type, Serialize, Deserialize)]
-#[sqlx(type_name = "order_status", rename_all = "lowercase")]
+#[sqlx(type_name = "order_side", rename_all = "lowercase")]
 #[serde(rename_all = "lowercase")]
+pub enum OrderSide {
+    Bid,
+    Ask,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Type, Serialize, Deserialize)]
+#[sqlx(type_name = "order_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum OrderStatus {
+    Open,
     PartiallyFilled,
     Filled,
+    #[sqlx(rename = "canceled")]
+    #[serde(rename = "canceled")]
     Cancelled,
+    Expired,
 }
 
-#[derive(Debug, FromRow)]
+/// A row of the `orders` table: every order from placement until it is
+/// filled, cancelled or expires.
+#[derive(Debug, FromRow, Serialize)]
 pub struct CloseOrder {
     pub id: Uuid,
-    pub user_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub user_address: Option<String>,
     pub market_id: Uuid,
     #[sqlx(rename = "type")]
     pub share_type: ShareType,
+    pub side: Option<OrderSide>,
     pub price: f64,
+    /// Original order size.
+    #[sqlx(rename = "qty")]
     pub quantity: f64,
+    #[sqlx(rename = "filled_qty")]
     pub filled_quantity: f64,
+    pub avg_fill_price: Option<f64>,
     pub status: OrderStatus,
-    pub closed_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// An order together with the on-chain id of its market.
+#[derive(Debug, FromRow, Serialize)]
+pub struct OrderHistoryEntry {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub order: CloseOrder,
+    pub chain_market_id: String,
 }
@@ -22,6 +22,19 @@ pub enum MarketOutcome {
     NotDecided,
 }
 
+/// Orderings of the public market listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketSort {
+    /// Most recently created first.
+    #[default]
+    Newest,
+    /// Soonest to close first.
+    CloseTime,
+    /// Highest total traded volume first.
+    Volume,
+}
+
 #[derive(Debug, FromRow, Serialize, Deserialize)]
 pub struct Market {
     pub id: Uuid,
@@ -31,6 +44,9 @@ pub struct Market {
     pub yes_mint: String,
     pub no_mint: String,
     pub usdc_vault: String,
+    pub collateral_mint: String,
+    pub collateral_decimals: i16,
+    pub share_decimals: i16,
     pub status: MarketStatus,
     pub outcome: MarketOutcome,
     pub close_time: DateTime<Utc>,
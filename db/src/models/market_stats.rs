@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// Activity and pricing of one market. Prices are per outcome; the YES last
+/// price doubles as the market's implied probability.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct MarketStats {
+    #[serde(skip)]
+    pub market_id: String,
+    pub volume_24h: f64,
+    pub total_volume: f64,
+    pub trade_count: i64,
+    /// Complete YES/NO sets outstanding, in shares.
+    pub open_interest: f64,
+    pub yes_total: i64,
+    pub no_total: i64,
+    pub yes_last_price: Option<f64>,
+    pub no_last_price: Option<f64>,
+    pub yes_best_bid: Option<f64>,
+    pub yes_best_ask: Option<f64>,
+    pub no_best_bid: Option<f64>,
+    pub no_best_ask: Option<f64>,
+    /// Last price now minus the last price 24 hours ago.
+    pub yes_change_24h: Option<f64>,
+    pub no_change_24h: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+}
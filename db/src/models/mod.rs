@@ -1,3 +1,8 @@
+pub mod candle;
 pub mod market;
+pub mod market_stats;
 pub mod user;
-pub mod close_order;
\ No newline at end of file
+pub mod close_order;
+pub mod reconciliation;
+pub mod trade;
+pub mod transaction;
\ No newline at end of file
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct ReconciliationIssue {
+    pub id: Uuid,
+    pub market_id: String,
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+    pub detected_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+use crate::models::close_order::{OrderSide, ShareType};
+
+/// A matched fill between a resting (maker) and an incoming (taker) order.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct TradeRecord {
+    pub id: Uuid,
+    pub market_id: String,
+    pub outcome: ShareType,
+    pub taker_side: OrderSide,
+    pub maker_order_id: Uuid,
+    pub taker_order_id: Uuid,
+    pub buyer_address: String,
+    pub seller_address: String,
+    pub price: f64,
+    #[sqlx(rename = "qty")]
+    pub quantity: f64,
+    pub signature: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A wallet's traded volume in one outcome of one market.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct TradeSummary {
+    pub market_id: String,
+    pub outcome: ShareType,
+    pub bought_qty: f64,
+    pub bought_cost: f64,
+    pub sold_qty: f64,
+    pub sold_proceeds: f64,
+}
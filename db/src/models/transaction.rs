@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::{FromRow, Type};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Type, Serialize, Deserialize)]
+#[sqlx(type_name = "tx_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    Pending,
+    Confirmed,
+    Finalized,
+    Failed,
+    Expired,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Type, Serialize, Deserialize)]
+#[sqlx(type_name = "tx_purpose", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TxPurpose {
+    CreateMarket,
+    PlaceOrder,
+    Split,
+    Merge,
+    SetWinner,
+    DelegateApproval,
+}
+
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct TrackedTransaction {
+    pub id: Uuid,
+    pub signature: String,
+    pub purpose: TxPurpose,
+    pub market_id: Option<String>,
+    pub user_address: Option<String>,
+    pub status: TxStatus,
+    pub error: Option<String>,
+    #[serde(skip_serializing)]
+    pub serialized_tx: Option<String>,
+    pub last_valid_block_height: i64,
+    pub nonce_account: Option<String>,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,14 @@
+use sqlx::{Error, PgPool};
+
+/// Channel carrying the wallet address of every account whose token balances
+/// changed on chain.
+pub const ACCOUNT_EVENTS_CHANNEL: &str = "account_events";
+
+pub async fn notify_account_event(pool: &PgPool, owner: &str) -> Result<(), Error> {
+    sqlx::query(r#"SELECT pg_notify($1, $2)"#)
+        .bind(ACCOUNT_EVENTS_CHANNEL)
+        .bind(owner)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Error, PgPool};
+
+use crate::models::{candle::CandleRow, close_order::ShareType};
+
+// Buckets are aligned to the unix epoch. `from` must sit on a bucket boundary
+// and `to` is exclusive; every bucket in between is returned, empty or not.
+
+/// OHLCV candles of the trades whose settlement transaction confirmed.
+pub async fn trade_candles(
+    pool: &PgPool,
+    market_id: &str,
+    outcome: ShareType,
+    bucket_secs: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<CandleRow>, Error> {
+    let recs = sqlx::query_as::<_, CandleRow>(
+        r#"WITH agg AS (
+            SELECT date_bin(make_interval(secs => $3), t.created_at, TIMESTAMPTZ '1970-01-01 00:00:00+00') AS bucket_start,
+                (array_agg(t.price ORDER BY t.created_at, t.id))[1] AS open,
+                MAX(t.price) AS high,
+                MIN(t.price) AS low,
+                (array_agg(t.price ORDER BY t.created_at DESC, t.id DESC))[1] AS close,
+                SUM(t.qty) AS volume,
+                COUNT(*) AS trade_count
+            FROM trades t
+            JOIN transactions tx ON tx.signature = t.signature
+            WHERE t.market_id = $1 AND t.outcome = $2
+                AND tx.status IN ('confirmed', 'finalized')
+                AND t.created_at >= $4 AND t.created_at < $5
+            GROUP BY 1
+        )
+        SELECT b.bucket_start, agg.open, agg.high, agg.low, agg.close,
+            COALESCE(agg.volume, 0) AS volume, COALESCE(agg.trade_count, 0) AS trade_count
+        FROM generate_series($4, $5 - make_interval(secs => $3), make_interval(secs => $3)) AS b(bucket_start)
+        LEFT JOIN agg USING (bucket_start)
+        ORDER BY b.bucket_start"#,
+    )
+    .bind(market_id)
+    .bind(outcome)
+    .bind(bucket_secs as f64)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
+
+/// Open/high/low/close of the sampled mid price.
+pub async fn mid_candles(
+    pool: &PgPool,
+    market_id: &str,
+    outcome: ShareType,
+    bucket_secs: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<CandleRow>, Error> {
+    let recs = sqlx::query_as::<_, CandleRow>(
+        r#"WITH agg AS (
+            SELECT date_bin(make_interval(secs => $3), sampled_at, TIMESTAMPTZ '1970-01-01 00:00:00+00') AS bucket_start,
+                (array_agg(mid ORDER BY sampled_at))[1] AS open,
+                MAX(mid) AS high,
+                MIN(mid) AS low,
+                (array_agg(mid ORDER BY sampled_at DESC))[1] AS close
+            FROM mid_price_samples
+            WHERE market_id = $1 AND outcome = $2
+                AND sampled_at >= $4 AND sampled_at < $5
+            GROUP BY 1
+        )
+        SELECT b.bucket_start, agg.open, agg.high, agg.low, agg.close,
+            0::float8 AS volume, 0::int8 AS trade_count
+        FROM generate_series($4, $5 - make_interval(secs => $3), make_interval(secs => $3)) AS b(bucket_start)
+        LEFT JOIN agg USING (bucket_start)
+        ORDER BY b.bucket_start"#,
+    )
+    .bind(market_id)
+    .bind(outcome)
+    .bind(bucket_secs as f64)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
+
+/// Price of the last confirmed trade before `before`, to carry into empty buckets.
+pub async fn last_trade_price_before(
+    pool: &PgPool,
+    market_id: &str,
+    outcome: ShareType,
+    before: DateTime<Utc>,
+) -> Result<Option<f64>, Error> {
+    sqlx::query_scalar::<_, f64>(
+        r#"SELECT t.price FROM trades t
+        JOIN transactions tx ON tx.signature = t.signature
+        WHERE t.market_id = $1 AND t.outcome = $2
+            AND tx.status IN ('confirmed', 'finalized')
+            AND t.created_at < $3
+        ORDER BY t.created_at DESC, t.id DESC LIMIT 1"#,
+    )
+    .bind(market_id)
+    .bind(outcome)
+    .bind(before)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Last sampled mid price before `before`.
+pub async fn last_mid_before(
+    pool: &PgPool,
+    market_id: &str,
+    outcome: ShareType,
+    before: DateTime<Utc>,
+) -> Result<Option<f64>, Error> {
+    sqlx::query_scalar::<_, f64>(
+        r#"SELECT mid FROM mid_price_samples
+        WHERE market_id = $1 AND outcome = $2 AND sampled_at < $3
+        ORDER BY sampled_at DESC LIMIT 1"#,
+    )
+    .bind(market_id)
+    .bind(outcome)
+    .bind(before)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn record_mid_price(
+    pool: &PgPool,
+    market_id: &str,
+    outcome: ShareType,
+    best_bid: f64,
+    best_ask: f64,
+    mid: f64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"INSERT INTO mid_price_samples (market_id, outcome, best_bid, best_ask, mid)
+        VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING"#,
+    )
+    .bind(market_id)
+    .bind(outcome)
+    .bind(best_bid)
+    .bind(best_ask)
+    .bind(mid)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
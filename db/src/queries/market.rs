@@ -1,9 +1,9 @@
 use chrono::{Date, DateTime, NaiveDateTime, Utc};
-use sqlx::{Error, PgPool};
+use sqlx::{Error, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
 use crate::{
-    models::market::{self, Market, MarketOutcome, MarketStatus},
+    models::market::{self, Market, MarketOutcome, MarketSort, MarketStatus},
     utils::fetch_metadata::fetch_market_metadata,
 };
 
@@ -15,6 +15,9 @@ pub async fn create_market(
     yes_mint: &str,
     no_mint: &str,
     usdc_vault: &str,
+    collateral_mint: &str,
+    collateral_decimals: i16,
+    share_decimals: i16,
     status: MarketStatus,
     outcome: MarketOutcome,
     close_time: DateTime<Utc>,
@@ -24,8 +27,8 @@ pub async fn create_market(
         sqlx::Error::Protocol(format!("Failed to fetch market metadata: {}", e).into())
     })?;
     let rec = sqlx::query_as::<_, Market>(
-        r#"INSERT INTO markets (market_id, market_pda, metadata_url, yes_mint, no_mint, usdc_vault, status, outcome, close_time, title, description, category, image_url, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14) 
-        RETURNING id, market_id, market_pda, metadata_url, yes_mint, no_mint, usdc_vault, status, outcome, close_time, resolve_time, title, description, category, image_url, created_at, updated_at"#,
+        r#"INSERT INTO markets (market_id, market_pda, metadata_url, yes_mint, no_mint, usdc_vault, collateral_mint, collateral_decimals, share_decimals, status, outcome, close_time, title, description, category, image_url, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17) 
+        RETURNING id, market_id, market_pda, metadata_url, yes_mint, no_mint, usdc_vault, collateral_mint, collateral_decimals, share_decimals, status, outcome, close_time, resolve_time, title, description, category, image_url, created_at, updated_at"#,
     )
     .bind(market_id)
     .bind(market_pda)
@@ -33,6 +36,9 @@ pub async fn create_market(
     .bind(yes_mint)
     .bind(no_mint)
     .bind(usdc_vault)
+    .bind(collateral_mint)
+    .bind(collateral_decimals)
+    .bind(share_decimals)
     .bind(status)
     .bind(outcome)
     .bind(close_time)
@@ -58,6 +64,107 @@ pub async fn get_market_by_id(pool: &PgPool, market_id: String) -> Result<Market
     Ok(rec)
 }
 
+pub async fn list_markets_by_ids(pool: &PgPool, market_ids: &[String]) -> Result<Vec<Market>, Error> {
+    let recs = sqlx::query_as::<_, Market>(
+        r#"SELECT * FROM markets WHERE market_id = ANY($1)"#,
+    )
+    .bind(market_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
+
+/// Markets whose YES or NO mint is one of `mints`.
+pub async fn list_markets_by_share_mints(pool: &PgPool, mints: &[String]) -> Result<Vec<Market>, Error> {
+    let recs = sqlx::query_as::<_, Market>(
+        r#"SELECT * FROM markets WHERE yes_mint = ANY($1) OR no_mint = ANY($1)"#,
+    )
+    .bind(mints)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
+
+const MARKET_COLUMNS: &str = "m.id, m.market_id, m.market_pda, m.metadata_url, m.yes_mint, \
+    m.no_mint, m.usdc_vault, m.collateral_mint, m.collateral_decimals, m.share_decimals, \
+    m.status, m.outcome, m.close_time, m.resolve_time, m.title, m.description, m.category, \
+    m.image_url, m.created_at, m.updated_at";
+
+/// Filters of the public market listing; `None` matches every market.
+#[derive(Debug, Default)]
+pub struct MarketFilter<'a> {
+    pub status: Option<MarketStatus>,
+    pub category: Option<&'a str>,
+    /// Web-search style query over title and description.
+    pub search: Option<&'a str>,
+    pub close_after: Option<DateTime<Utc>>,
+    pub close_before: Option<DateTime<Utc>>,
+}
+
+/// Sort key of the last market of the previous page: its created_at or
+/// close_time for the time orderings, its total volume for `Volume`.
+#[derive(Debug, Clone, Copy)]
+pub enum MarketKeyset {
+    Time(DateTime<Utc>),
+    Volume(f64),
+}
+
+/// One page of markets matching `filter`. `after` is the keyset of the last
+/// row of the previous page, in the same `sort`.
+pub async fn search_markets(
+    pool: &PgPool,
+    filter: &MarketFilter<'_>,
+    sort: MarketSort,
+    after: Option<(MarketKeyset, Uuid)>,
+    limit: i64,
+) -> Result<Vec<Market>, Error> {
+    let mut query = QueryBuilder::<Postgres>::new(format!(
+        "SELECT {} FROM markets m LEFT JOIN market_stats s ON s.market_id = m.market_id WHERE TRUE",
+        MARKET_COLUMNS
+    ));
+    if let Some(status) = &filter.status {
+        query.push(" AND m.status = ").push_bind(status.clone());
+    }
+    if let Some(category) = filter.category {
+        query.push(" AND m.category = ").push_bind(category);
+    }
+    if let Some(search) = filter.search {
+        query
+            .push(" AND m.search_vector @@ websearch_to_tsquery('english', ")
+            .push_bind(search)
+            .push(")");
+    }
+    if let Some(close_after) = filter.close_after {
+        query.push(" AND m.close_time >= ").push_bind(close_after);
+    }
+    if let Some(close_before) = filter.close_before {
+        query.push(" AND m.close_time < ").push_bind(close_before);
+    }
+
+    let (key, direction) = match sort {
+        MarketSort::Newest => ("m.created_at", "DESC"),
+        MarketSort::CloseTime => ("m.close_time", "ASC"),
+        MarketSort::Volume => ("COALESCE(s.total_volume, 0)", "DESC"),
+    };
+    if let Some((keyset, id)) = after {
+        let op = if direction == "DESC" { "<" } else { ">" };
+        query.push(format!(" AND ({}, m.id) {} (", key, op));
+        match keyset {
+            MarketKeyset::Time(t) => query.push_bind(t),
+            MarketKeyset::Volume(v) => query.push_bind(v),
+        };
+        query.push(", ").push_bind(id).push(")");
+    }
+    query.push(format!(" ORDER BY {} {}, m.id {} LIMIT ", key, direction, direction));
+    query.push_bind(limit);
+
+    let recs = query.build_query_as::<Market>().fetch_all(pool).await?;
+
+    Ok(recs)
+}
+
 pub async fn list_markets_by_status(pool: &PgPool, status: MarketStatus) -> Result<Vec<Market>, Error> {
     let recs = sqlx::query_as::<_, Market>(
         r#"SELECT * FROM markets WHERE status = $1"#,
@@ -79,6 +186,33 @@ pub async fn list_all_markets(pool: &PgPool) -> Result<Vec<Market>, Error> {
     Ok(recs)
 }
 
+/// Open markets whose close time has passed.
+pub async fn list_markets_due_for_close(
+    pool: &PgPool,
+    now: DateTime<Utc>,
+) -> Result<Vec<Market>, Error> {
+    let recs = sqlx::query_as::<_, Market>(
+        r#"SELECT * FROM markets WHERE status = 'open' AND close_time <= $1"#,
+    )
+    .bind(now)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
+
+/// Moves an open market to closed. Returns false if it was no longer open.
+pub async fn close_market(pool: &PgPool, market_id: &str) -> Result<bool, Error> {
+    let res = sqlx::query(
+        r#"UPDATE markets SET status = 'closed', updated_at = NOW() WHERE market_id = $1 AND status = 'open'"#,
+    )
+    .bind(market_id)
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
 pub async fn update_market_resolution(
     pool: &PgPool,
     market_id: String,
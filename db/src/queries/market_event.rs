@@ -0,0 +1,26 @@
+use sqlx::{Error, PgPool};
+
+/// Channel carrying the id of every market whose settlement landed on chain.
+pub const MARKET_SETTLED_CHANNEL: &str = "market_settled";
+
+pub async fn notify_market_settled(pool: &PgPool, market_id: &str) -> Result<(), Error> {
+    sqlx::query(r#"SELECT pg_notify($1, $2)"#)
+        .bind(MARKET_SETTLED_CHANNEL)
+        .bind(market_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Channel carrying the id of every market whose share supply may have
+/// changed on chain (splits, merges, claims).
+pub const MARKET_ACTIVITY_CHANNEL: &str = "market_activity";
+
+pub async fn notify_market_activity(pool: &PgPool, market_id: &str) -> Result<(), Error> {
+    sqlx::query(r#"SELECT pg_notify($1, $2)"#)
+        .bind(MARKET_ACTIVITY_CHANNEL)
+        .bind(market_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Error, PgPool};
+
+use crate::models::{close_order::ShareType, market_stats::MarketStats};
+
+/// Adds one trade to the market's volume and moves its last price.
+pub async fn record_trade_stats(
+    pool: &PgPool,
+    market_id: &str,
+    outcome: ShareType,
+    price: f64,
+    quantity: f64,
+) -> Result<(), Error> {
+    let (yes_price, no_price) = match outcome {
+        ShareType::Yes => (Some(price), None),
+        ShareType::No => (None, Some(price)),
+    };
+    sqlx::query(
+        r#"INSERT INTO market_stats (market_id, total_volume, trade_count, yes_last_price, no_last_price)
+        VALUES ($1, $2, 1, $3, $4)
+        ON CONFLICT (market_id) DO UPDATE SET
+            total_volume = market_stats.total_volume + EXCLUDED.total_volume,
+            trade_count = market_stats.trade_count + 1,
+            yes_last_price = COALESCE(EXCLUDED.yes_last_price, market_stats.yes_last_price),
+            no_last_price = COALESCE(EXCLUDED.no_last_price, market_stats.no_last_price),
+            updated_at = CURRENT_TIMESTAMP"#,
+    )
+    .bind(market_id)
+    .bind(quantity)
+    .bind(yes_price)
+    .bind(no_price)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Replaces the top of both books.
+pub async fn set_book_top(
+    pool: &PgPool,
+    market_id: &str,
+    yes: (Option<f64>, Option<f64>),
+    no: (Option<f64>, Option<f64>),
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"INSERT INTO market_stats (market_id, yes_best_bid, yes_best_ask, no_best_bid, no_best_ask)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (market_id) DO UPDATE SET
+            yes_best_bid = EXCLUDED.yes_best_bid,
+            yes_best_ask = EXCLUDED.yes_best_ask,
+            no_best_bid = EXCLUDED.no_best_bid,
+            no_best_ask = EXCLUDED.no_best_ask,
+            updated_at = CURRENT_TIMESTAMP"#,
+    )
+    .bind(market_id)
+    .bind(yes.0)
+    .bind(yes.1)
+    .bind(no.0)
+    .bind(no.1)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Stores the share supplies read from the market account.
+pub async fn set_open_interest(
+    pool: &PgPool,
+    market_id: &str,
+    yes_total: i64,
+    no_total: i64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"INSERT INTO market_stats (market_id, yes_total, no_total)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (market_id) DO UPDATE SET
+            yes_total = EXCLUDED.yes_total,
+            no_total = EXCLUDED.no_total,
+            updated_at = CURRENT_TIMESTAMP"#,
+    )
+    .bind(market_id)
+    .bind(yes_total)
+    .bind(no_total)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Stats of the given markets; markets without any activity yet have no row.
+pub async fn list_market_stats(
+    pool: &PgPool,
+    market_ids: &[String],
+    now: DateTime<Utc>,
+) -> Result<Vec<MarketStats>, Error> {
+    let recs = sqlx::query_as::<_, MarketStats>(
+        r#"SELECT s.market_id,
+            COALESCE(v.volume_24h, 0) AS volume_24h,
+            s.total_volume, s.trade_count,
+            s.yes_total::float8 / power(10, m.share_decimals) AS open_interest,
+            s.yes_total, s.no_total,
+            s.yes_last_price, s.no_last_price,
+            s.yes_best_bid, s.yes_best_ask, s.no_best_bid, s.no_best_ask,
+            s.yes_last_price - y.price AS yes_change_24h,
+            s.no_last_price - n.price AS no_change_24h,
+            s.updated_at
+        FROM market_stats s
+        JOIN markets m ON m.market_id = s.market_id
+        LEFT JOIN LATERAL (
+            SELECT SUM(qty) AS volume_24h FROM trades
+            WHERE market_id = s.market_id AND created_at >= $2 - INTERVAL '24 hours'
+        ) v ON true
+        LEFT JOIN LATERAL (
+            SELECT price FROM trades
+            WHERE market_id = s.market_id AND outcome = 'yes'
+                AND created_at < $2 - INTERVAL '24 hours'
+            ORDER BY created_at DESC, id DESC LIMIT 1
+        ) y ON true
+        LEFT JOIN LATERAL (
+            SELECT price FROM trades
+            WHERE market_id = s.market_id AND outcome = 'no'
+                AND created_at < $2 - INTERVAL '24 hours'
+            ORDER BY created_at DESC, id DESC LIMIT 1
+        ) n ON true
+        WHERE s.market_id = ANY($1)"#,
+    )
+    .bind(market_ids)
+    .bind(now)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
@@ -1,3 +1,10 @@
+pub mod account_event;
+pub mod candle;
 pub mod market;
+pub mod market_event;
+pub mod market_stats;
 pub mod order;
+pub mod reconciliation;
+pub mod trade;
+pub mod transaction;
 pub mod user;
\ No newline at end of file
@@ -2,7 +2,100 @@ use chrono::{DateTime, Utc};
 use sqlx::{Error, PgPool};
 use uuid::Uuid;
 
-use crate::models::close_order::{CloseOrder, OrderStatus, ShareType};
+use crate::models::close_order::{CloseOrder, OrderHistoryEntry, OrderSide, OrderStatus, ShareType};
+
+/// Records a newly accepted order as open.
+pub async fn create_order(
+    pool: &PgPool,
+    id: Uuid,
+    market_id: Uuid,
+    user_id: Option<Uuid>,
+    user_address: &str,
+    share_type: ShareType,
+    side: OrderSide,
+    price: f64,
+    quantity: f64,
+) -> Result<CloseOrder, Error> {
+    let rec = sqlx::query_as::<_, CloseOrder>(
+        r#"INSERT INTO orders (id, market_id, user_id, user_address, type, side, price, qty, filled_qty, status)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 0, 'open') RETURNING *"#,
+    )
+    .bind(id)
+    .bind(market_id)
+    .bind(user_id)
+    .bind(user_address)
+    .bind(share_type)
+    .bind(side)
+    .bind(price)
+    .bind(quantity)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rec)
+}
+
+/// Adds a fill of `quantity` at `price` to an order, keeping the average fill
+/// price and moving it to partially filled or filled.
+pub async fn record_order_fill(
+    pool: &PgPool,
+    id: Uuid,
+    quantity: f64,
+    price: f64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"UPDATE orders SET
+            avg_fill_price = (COALESCE(avg_fill_price, 0) * filled_qty + $2 * $3) / (filled_qty + $2),
+            filled_qty = filled_qty + $2,
+            status = CASE WHEN filled_qty + $2 >= qty THEN 'filled'::order_status
+                ELSE 'partially_filled'::order_status END,
+            closed_at = CASE WHEN filled_qty + $2 >= qty THEN NOW() ELSE closed_at END,
+            updated_at = NOW()
+        WHERE id = $1"#,
+    )
+    .bind(id)
+    .bind(quantity)
+    .bind(price)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Moves a still-resting order to `price` with `open_qty` left to fill. Returns
+/// false if it had already closed.
+pub async fn amend_open_order(
+    pool: &PgPool,
+    id: Uuid,
+    price: f64,
+    open_qty: f64,
+) -> Result<bool, Error> {
+    let res = sqlx::query(
+        r#"UPDATE orders SET price = $2, qty = filled_qty + $3, updated_at = NOW()
+        WHERE id = $1 AND status IN ('open', 'partially_filled')"#,
+    )
+    .bind(id)
+    .bind(price)
+    .bind(open_qty)
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+/// Closes a still-resting order as cancelled or expired. Returns false if no
+/// such resting order is recorded.
+pub async fn close_open_order(pool: &PgPool, id: Uuid, status: OrderStatus) -> Result<bool, Error> {
+    let res = sqlx::query(
+        r#"UPDATE orders SET status = $2, closed_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND status IN ('open', 'partially_filled')"#,
+    )
+    .bind(id)
+    .bind(status)
+    .execute(pool)
+    .await?;
+
+    Ok(res.rows_affected() > 0)
+}
 
 pub async fn create_close_order(
     pool: &PgPool,
@@ -18,7 +111,7 @@ pub async fn create_close_order(
 ) -> Result<CloseOrder, Error> {
     let rec = sqlx::query_as::<_, CloseOrder>(
         r#"INSERT INTO orders (id, market_id, user_id, type, price, qty, filled_qty, status, closed_at) 
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id, market_id, user_id, type, price, qty, filled_qty, status, closed_at"#,
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *"#,
     )
     .bind(id)
     .bind(market_id)
@@ -37,8 +130,7 @@ pub async fn create_close_order(
 
 pub async fn get_close_order_by_id(pool: &PgPool, id: Uuid) -> Result<CloseOrder, Error> {
     let rec = sqlx::query_as::<_, CloseOrder>(
-        r#"SELECT id, market_id, user_id, type, price, qty, filled_qty, status, closed_at 
-        FROM orders WHERE id = $1"#,
+        r#"SELECT * FROM orders WHERE id = $1"#,
     )
     .bind(id)
     .fetch_one(pool)
@@ -52,8 +144,7 @@ pub async fn list_close_orders_by_market_id(
     market_id: Uuid,
 ) -> Result<Vec<CloseOrder>, Error> {
     let recs = sqlx::query_as::<_, CloseOrder>(
-        r#"SELECT id, market_id, user_id, type, price, qty, filled_qty, status, closed_at 
-        FROM orders WHERE market_id = $1"#,
+        r#"SELECT * FROM orders WHERE market_id = $1"#,
     )
     .bind(market_id)
     .fetch_all(pool)
@@ -62,15 +153,43 @@ pub async fn list_close_orders_by_market_id(
     Ok(recs)
 }
 
-pub async fn list_closed_orders_by_user_id(
+/// A user's orders, newest first. Rows recorded before orders carried the
+/// owner's address are matched through `user_id`. `before` is the
+/// (created_at, id) keyset cursor of the last row of the previous page.
+pub async fn list_orders_by_user(
     pool: &PgPool,
-    user_id: Uuid,
-) -> Result<Vec<CloseOrder>, Error> {
-    let recs = sqlx::query_as::<_, CloseOrder>(
-        r#"SELECT id, market_id, user_id, type, price, qty, filled_qty, status, closed_at 
-        FROM orders WHERE user_id = $1"#,
+    user_id: Option<Uuid>,
+    user_address: &str,
+    market_id: Option<&str>,
+    status: Option<OrderStatus>,
+    side: Option<OrderSide>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    before: Option<(DateTime<Utc>, Uuid)>,
+    limit: i64,
+) -> Result<Vec<OrderHistoryEntry>, Error> {
+    let recs = sqlx::query_as::<_, OrderHistoryEntry>(
+        r#"SELECT o.*, m.market_id AS chain_market_id
+        FROM orders o JOIN markets m ON m.id = o.market_id
+        WHERE (o.user_address = $2 OR ($1::uuid IS NOT NULL AND o.user_id = $1))
+            AND ($3::text IS NULL OR m.market_id = $3)
+            AND ($4::order_status IS NULL OR o.status = $4)
+            AND ($5::order_side IS NULL OR o.side = $5)
+            AND ($6::timestamptz IS NULL OR o.created_at >= $6)
+            AND ($7::timestamptz IS NULL OR o.created_at < $7)
+            AND ($8::timestamptz IS NULL OR (o.created_at, o.id) < ($8, $9::uuid))
+        ORDER BY o.created_at DESC, o.id DESC LIMIT $10"#,
     )
     .bind(user_id)
+    .bind(user_address)
+    .bind(market_id)
+    .bind(status)
+    .bind(side)
+    .bind(from)
+    .bind(to)
+    .bind(before.map(|(t, _)| t))
+    .bind(before.map(|(_, id)| id))
+    .bind(limit)
     .fetch_all(pool)
     .await?;
 
@@ -0,0 +1,63 @@
+use sqlx::{Error, PgPool};
+
+use crate::models::reconciliation::ReconciliationIssue;
+
+/// Opens an issue for `market_id`/`field`, or refreshes the values of the one
+/// already open.
+pub async fn record_mismatch(
+    pool: &PgPool,
+    market_id: &str,
+    field: &str,
+    expected: &str,
+    actual: &str,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"INSERT INTO market_reconciliation_issues (market_id, field, expected, actual)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (market_id, field) WHERE resolved_at IS NULL
+        DO UPDATE SET expected = EXCLUDED.expected, actual = EXCLUDED.actual, last_seen_at = NOW()"#,
+    )
+    .bind(market_id)
+    .bind(field)
+    .bind(expected)
+    .bind(actual)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Resolves the open issues of a market whose field is not in `still_open`.
+pub async fn resolve_mismatches(
+    pool: &PgPool,
+    market_id: &str,
+    still_open: &[String],
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"UPDATE market_reconciliation_issues SET resolved_at = NOW()
+        WHERE market_id = $1 AND resolved_at IS NULL AND NOT (field = ANY($2))"#,
+    )
+    .bind(market_id)
+    .bind(still_open)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list_issues(
+    pool: &PgPool,
+    include_resolved: bool,
+    limit: i64,
+) -> Result<Vec<ReconciliationIssue>, Error> {
+    let recs = sqlx::query_as::<_, ReconciliationIssue>(
+        r#"SELECT * FROM market_reconciliation_issues WHERE ($1 OR resolved_at IS NULL)
+        ORDER BY last_seen_at DESC LIMIT $2"#,
+    )
+    .bind(include_resolved)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
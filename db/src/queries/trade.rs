@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::models::{
+    close_order::{OrderSide, ShareType},
+    trade::{TradeRecord, TradeSummary},
+};
+
+pub async fn create_trade(
+    pool: &PgPool,
+    market_id: &str,
+    outcome: ShareType,
+    taker_side: OrderSide,
+    maker_order_id: Uuid,
+    taker_order_id: Uuid,
+    buyer_address: &str,
+    seller_address: &str,
+    price: f64,
+    quantity: f64,
+    signature: Option<&str>,
+) -> Result<TradeRecord, Error> {
+    let rec = sqlx::query_as::<_, TradeRecord>(
+        r#"INSERT INTO trades (market_id, outcome, taker_side, maker_order_id, taker_order_id,
+            buyer_address, seller_address, price, qty, signature)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING *"#,
+    )
+    .bind(market_id)
+    .bind(outcome)
+    .bind(taker_side)
+    .bind(maker_order_id)
+    .bind(taker_order_id)
+    .bind(buyer_address)
+    .bind(seller_address)
+    .bind(price)
+    .bind(quantity)
+    .bind(signature)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rec)
+}
+
+/// Fills a wallet took part in, newest first. `side` is the wallet's side of
+/// the trade (bid = it bought). `before` is the (created_at, id) keyset cursor
+/// of the last row of the previous page.
+pub async fn list_trades_by_user(
+    pool: &PgPool,
+    user_address: &str,
+    market_id: Option<&str>,
+    side: Option<OrderSide>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    before: Option<(DateTime<Utc>, Uuid)>,
+    limit: i64,
+) -> Result<Vec<TradeRecord>, Error> {
+    let recs = sqlx::query_as::<_, TradeRecord>(
+        r#"SELECT * FROM trades
+        WHERE (buyer_address = $1 OR seller_address = $1)
+            AND ($2::text IS NULL OR market_id = $2)
+            AND ($3::order_side IS NULL
+                OR ($3 = 'bid' AND buyer_address = $1)
+                OR ($3 = 'ask' AND seller_address = $1))
+            AND ($4::timestamptz IS NULL OR created_at >= $4)
+            AND ($5::timestamptz IS NULL OR created_at < $5)
+            AND ($6::timestamptz IS NULL OR (created_at, id) < ($6, $7::uuid))
+        ORDER BY created_at DESC, id DESC LIMIT $8"#,
+    )
+    .bind(user_address)
+    .bind(market_id)
+    .bind(side)
+    .bind(from)
+    .bind(to)
+    .bind(before.map(|(t, _)| t))
+    .bind(before.map(|(_, id)| id))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
+
+/// Buy and sell totals of a wallet per market and outcome.
+pub async fn summarize_user_trades(
+    pool: &PgPool,
+    user_address: &str,
+) -> Result<Vec<TradeSummary>, Error> {
+    let recs = sqlx::query_as::<_, TradeSummary>(
+        r#"SELECT market_id, outcome,
+            COALESCE(SUM(qty) FILTER (WHERE buyer_address = $1), 0) AS bought_qty,
+            COALESCE(SUM(qty * price) FILTER (WHERE buyer_address = $1), 0) AS bought_cost,
+            COALESCE(SUM(qty) FILTER (WHERE seller_address = $1), 0) AS sold_qty,
+            COALESCE(SUM(qty * price) FILTER (WHERE seller_address = $1), 0) AS sold_proceeds
+        FROM trades
+        WHERE buyer_address = $1 OR seller_address = $1
+        GROUP BY market_id, outcome"#,
+    )
+    .bind(user_address)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
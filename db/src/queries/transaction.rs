@@ -0,0 +1,87 @@
+use sqlx::{Error, PgPool};
+
+use crate::models::transaction::{TrackedTransaction, TxPurpose, TxStatus};
+
+pub async fn create_transaction(
+    pool: &PgPool,
+    signature: &str,
+    purpose: TxPurpose,
+    market_id: Option<&str>,
+    user_address: Option<&str>,
+    serialized_tx: Option<&str>,
+    last_valid_block_height: i64,
+    nonce_account: Option<&str>,
+) -> Result<TrackedTransaction, Error> {
+    let rec = sqlx::query_as::<_, TrackedTransaction>(
+        r#"INSERT INTO transactions (signature, purpose, market_id, user_address, serialized_tx, last_valid_block_height, nonce_account)
+        VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *"#,
+    )
+    .bind(signature)
+    .bind(purpose)
+    .bind(market_id)
+    .bind(user_address)
+    .bind(serialized_tx)
+    .bind(last_valid_block_height)
+    .bind(nonce_account)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rec)
+}
+
+/// Transactions that have not reached a terminal state yet.
+pub async fn list_unsettled_transactions(pool: &PgPool) -> Result<Vec<TrackedTransaction>, Error> {
+    let recs = sqlx::query_as::<_, TrackedTransaction>(
+        r#"SELECT * FROM transactions WHERE status IN ('pending', 'confirmed') ORDER BY created_at"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
+
+pub async fn list_transactions(
+    pool: &PgPool,
+    status: Option<TxStatus>,
+    limit: i64,
+) -> Result<Vec<TrackedTransaction>, Error> {
+    let recs = sqlx::query_as::<_, TrackedTransaction>(
+        r#"SELECT * FROM transactions WHERE ($1::tx_status IS NULL OR status = $1)
+        ORDER BY created_at DESC LIMIT $2"#,
+    )
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(recs)
+}
+
+pub async fn update_transaction_status(
+    pool: &PgPool,
+    signature: &str,
+    status: TxStatus,
+    error: Option<String>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"UPDATE transactions SET status = $1, error = $2, updated_at = NOW() WHERE signature = $3"#,
+    )
+    .bind(status)
+    .bind(error)
+    .bind(signature)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn increment_transaction_attempts(pool: &PgPool, signature: &str) -> Result<(), Error> {
+    sqlx::query(
+        r#"UPDATE transactions SET attempts = attempts + 1, updated_at = NOW() WHERE signature = $1"#,
+    )
+    .bind(signature)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
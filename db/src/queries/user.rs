@@ -33,4 +33,15 @@ pub async fn get_user_by_email(pool: &PgPool, email: &str) -> Result<User, Error
     .await?;
 
     Ok(rec)
-}
\ No newline at end of file
+}
+
+pub async fn get_user_by_solana_address(pool: &PgPool, solana_address: &str) -> Result<User, Error> {
+    let rec = sqlx::query_as::<_, User>(
+        r#"SELECT id, name, email, solana_address, created_at FROM users WHERE solana_address = $1"#,
+    )
+    .bind(solana_address)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rec)
+}
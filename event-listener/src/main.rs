@@ -1,19 +1,25 @@
-use anchor_client_sdk::predix_program::types::MarketOutcome;
+use anchor_client_sdk::{
+    PredixConfig, predix_program::types::MarketOutcome, utils::fetch_mint_decimals,
+};
 use anchor_lang::prelude::*;
 use chrono::Utc;
 use db::{
     Db,
     models::market::{self, MarketStatus},
-    queries::market::{create_market, update_market_resolution},
+    queries::{
+        account_event::notify_account_event,
+        market::{create_market, update_market_resolution},
+        market_event::{notify_market_activity, notify_market_settled},
+    },
 };
-use std::{path::Path, str::FromStr};
+use std::path::Path;
 
 use anyhow::Result;
 use solana_client::{
-    nonblocking::pubsub_client::PubsubClient,
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
     rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
 };
-use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
 
 use tokio_stream::StreamExt;
 
@@ -21,21 +27,43 @@ use crate::types::MarketInitialized;
 use dotenvy::from_path;
 mod types;
 
+const MINT_FETCH_ATTEMPTS: u32 = 3;
+
+/// Reads a mint's decimals, retrying transient RPC failures so a single bad
+/// response doesn't drop the market.
+async fn mint_decimals(rpc: &RpcClient, mint: &Pubkey) -> Result<u8> {
+    let mut attempt = 1;
+    loop {
+        match fetch_mint_decimals(rpc, mint).await {
+            Ok(decimals) => return Ok(decimals),
+            Err(e) if attempt < MINT_FETCH_ATTEMPTS => {
+                println!(
+                    "Failed to read decimals of mint {} (attempt {}): {}",
+                    mint, attempt, e
+                );
+                tokio::time::sleep(Duration::from_millis(500 * attempt as u64)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     from_path(Path::new("../.env")).ok();
 
     let database_url = std::env::var("DATABASE_URL")?;
-    let program_id = std::env::var("PROGRAM_ID")?;
-    let rpc_url =
-        std::env::var("SOLANA_WS_RPC_URL").unwrap_or("wss://api.devnet.solana.com/".to_string());
+    let predix_config = PredixConfig::from_env()?;
+    let program_id = predix_config.program_id;
 
-    let program_id =
-        Pubkey::from_str(&program_id).map_err(|e| anyhow::anyhow!("Invalid program id: {}", e))?;
     let pool = Db::new(&database_url).await?.pool;
-    let client = PubsubClient::new(&rpc_url).await?;
+    let rpc = predix_config.rpc_client();
+    let client = PubsubClient::new(&predix_config.ws_url).await?;
     let filter = RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]);
-    let config = RpcTransactionLogsConfig { commitment: None };
+    let config = RpcTransactionLogsConfig {
+        commitment: Some(predix_config.commitment),
+    };
     let (mut log_stream, _unsubscribe) = client.logs_subscribe(filter, config).await?;
 
     let initialized_discriminator =
@@ -71,6 +99,24 @@ async fn main() -> Result<()> {
                                 let yes_mint = event.yes_mint.to_string();
                                 let no_mint = event.no_mint.to_string();
                                 let usdc_vault = event.collateral_vault.to_string();
+                                let collateral_mint = event.collateral_mint.to_string();
+                                let decimals = async {
+                                    let collateral =
+                                        mint_decimals(&rpc, &event.collateral_mint).await?;
+                                    let share = mint_decimals(&rpc, &event.yes_mint).await?;
+                                    anyhow::Ok((collateral, share))
+                                }
+                                .await;
+                                let (collateral_decimals, share_decimals) = match decimals {
+                                    Ok(decimals) => decimals,
+                                    Err(e) => {
+                                        println!(
+                                            "Skipping MarketInitialized for market {}: failed to read mint decimals: {}",
+                                            market_id, e
+                                        );
+                                        continue;
+                                    }
+                                };
                                 let status = MarketStatus::Open;
                                 let outcome = market::MarketOutcome::NotDecided;
                                 let close_time = chrono::DateTime::<Utc>::from_timestamp(
@@ -87,6 +133,9 @@ async fn main() -> Result<()> {
                                     &yes_mint,
                                     &no_mint,
                                     &usdc_vault,
+                                    &collateral_mint,
+                                    collateral_decimals as i16,
+                                    share_decimals as i16,
                                     status,
                                     outcome,
                                     close_time,
@@ -105,6 +154,17 @@ async fn main() -> Result<()> {
                         match crate::types::MatchExecuted::try_from_slice(payload) {
                             Ok(event) => {
                                 println!("Decoded MatchExecuted event: {:?}", event);
+                                // a missed notification only delays a balance refresh
+                                for owner in [event.buyer, event.seller] {
+                                    if let Err(e) =
+                                        notify_account_event(&pool, &owner.to_string()).await
+                                    {
+                                        println!(
+                                            "Failed to notify account event for {}: {}",
+                                            owner, e
+                                        );
+                                    }
+                                }
                             }
                             Err(e) => {
                                 println!("Failed to decode MatchExecuted event: {}", e);
@@ -116,6 +176,23 @@ async fn main() -> Result<()> {
                         match crate::types::TokensSplit::try_from_slice(payload) {
                             Ok(event) => {
                                 println!("Decoded TokensSplit event: {:?}", event);
+                                if let Err(e) =
+                                    notify_account_event(&pool, &event.user.to_string()).await
+                                {
+                                    println!(
+                                        "Failed to notify account event for {}: {}",
+                                        event.user, e
+                                    );
+                                }
+                                if let Err(e) =
+                                    notify_market_activity(&pool, &event.market_id.to_string())
+                                        .await
+                                {
+                                    println!(
+                                        "Failed to notify activity of market {}: {}",
+                                        event.market_id, e
+                                    );
+                                }
                             }
                             Err(e) => {
                                 println!("Failed to decode TokensSplit event: {}", e);
@@ -127,6 +204,23 @@ async fn main() -> Result<()> {
                         match crate::types::TokensMerged::try_from_slice(payload) {
                             Ok(event) => {
                                 println!("Decoded TokensMerged event: {:?}", event);
+                                if let Err(e) =
+                                    notify_account_event(&pool, &event.user.to_string()).await
+                                {
+                                    println!(
+                                        "Failed to notify account event for {}: {}",
+                                        event.user, e
+                                    );
+                                }
+                                if let Err(e) =
+                                    notify_market_activity(&pool, &event.market_id.to_string())
+                                        .await
+                                {
+                                    println!(
+                                        "Failed to notify activity of market {}: {}",
+                                        event.market_id, e
+                                    );
+                                }
                             }
                             Err(e) => {
                                 println!("Failed to decode TokensMerged event: {}", e);
@@ -138,6 +232,23 @@ async fn main() -> Result<()> {
                         match crate::types::RewardsClaimed::try_from_slice(payload) {
                             Ok(event) => {
                                 println!("Decoded RewardsClaimed event: {:?}", event);
+                                if let Err(e) =
+                                    notify_account_event(&pool, &event.user.to_string()).await
+                                {
+                                    println!(
+                                        "Failed to notify account event for {}: {}",
+                                        event.user, e
+                                    );
+                                }
+                                if let Err(e) =
+                                    notify_market_activity(&pool, &event.market_id.to_string())
+                                        .await
+                                {
+                                    println!(
+                                        "Failed to notify activity of market {}: {}",
+                                        event.market_id, e
+                                    );
+                                }
                             }
                             Err(e) => {
                                 println!("Failed to decode RewardsClaimed event: {}", e);
@@ -164,6 +275,14 @@ async fn main() -> Result<()> {
                                     resolve_time,
                                 )
                                 .await?;
+                                if let Err(e) =
+                                    notify_market_settled(&pool, &event.market_id.to_string()).await
+                                {
+                                    println!(
+                                        "Failed to notify settlement of market {}: {}",
+                                        event.market_id, e
+                                    );
+                                }
                                 println!("Decoded MarketSettled event: {:?}", event);
                                 dbg!("market settled event processed: {}", market);
                             }
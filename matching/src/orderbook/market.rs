@@ -1,5 +1,7 @@
 
-use crate::{orderbook::orderbook::OrderBook, types::{ OpenOrder, SnapshotData}};
+use uuid::Uuid;
+
+use crate::{orderbook::orderbook::OrderBook, types::{CancelFilter, OpenOrder, OrderEntry, Outcome, SnapshotData}};
 pub struct MarketBooks {
     pub yes: OrderBook,
     pub no: OrderBook,
@@ -67,42 +69,99 @@ impl MarketBooks {
         (yes, no)
     }
 
-    pub fn find_open_orders(&self, user_address: &String, market_id: &String) -> Vec<OpenOrder> {
-        let mut open_orders = Vec::new();
+    /// Empties both books, returning the removed (yes, no) orders.
+    pub fn drain(&mut self) -> (Vec<OrderEntry>, Vec<OrderEntry>) {
+        (self.yes.drain(), self.no.drain())
+    }
 
-        for orders in self.yes.bids.values().chain(self.yes.asks.values())
-        {
-            for order in orders {
-                if &order.user_address == user_address {
-                    let order = OpenOrder {
-                        id: order.id.clone(),
-                        market_id: market_id.clone(),
-                        outcome: "Yes".to_string(),
-                        side: order.side.clone(),
-                        price: order.price,
-                        quantity: order.qty,
-                    };
-                    open_orders.push(order);
-                }
+    /// Removes `order_id` from whichever outcome's book holds it, provided
+    /// `user_address` owns it.
+    pub fn cancel_owned(&mut self, order_id: Uuid, user_address: &str) -> Option<OrderEntry> {
+        for book in [&mut self.yes, &mut self.no] {
+            if book
+                .find_order(order_id)
+                .is_some_and(|o| o.user_address == user_address)
+            {
+                return book.remove_order(order_id);
             }
         }
-        for orders in self.no.bids.values().chain(self.no.asks.values())
-        {
-            for order in orders {
-                if &order.user_address == user_address {
-                    let order = OpenOrder {
-                        id: order.id.clone(),
-                        market_id: market_id.clone(),
-                        outcome: "No".to_string(),
-                        side: order.side.clone(),
-                        price: order.price,
-                        quantity: order.qty,
-                    };
-                    open_orders.push(order);
-                }
+        None
+    }
+
+    /// Removes every resting order matching `filter` from both outcomes.
+    pub fn cancel_all(&mut self, filter: &CancelFilter) -> Vec<OrderEntry> {
+        let mut removed = Vec::new();
+        for (outcome, book) in [(Outcome::Yes, &mut self.yes), (Outcome::No, &mut self.no)] {
+            if filter.outcome.is_some_and(|o| o != outcome) {
+                continue;
             }
+            removed.extend(book.remove_where(|order| filter.matches(order)));
         }
+        removed
+    }
 
+    /// The user's resting orders on both outcomes, oldest first.
+    pub fn find_open_orders(&self, user_address: &String, market_id: &String) -> Vec<OpenOrder> {
+        let mut open_orders = Vec::new();
+        for (outcome, book) in [(Outcome::Yes, &self.yes), (Outcome::No, &self.no)] {
+            for orders in book.bids.values().chain(book.asks.values()) {
+                for order in orders {
+                    if &order.user_address == user_address {
+                        open_orders.push(OpenOrder::from_entry(order, market_id, outcome));
+                    }
+                }
+            }
+        }
+        open_orders.sort_by_key(|o| (o.timestamp, o.sequence));
         open_orders
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::types::Side;
+
+    fn rest(book: &mut OrderBook, user: &str, side: Side, price: &str) -> Uuid {
+        let order = OrderEntry::new(
+            Uuid::new_v4(),
+            user.to_string(),
+            1,
+            side.clone(),
+            price.parse::<Decimal>().unwrap(),
+            Decimal::ONE,
+            0,
+        );
+        let id = order.id;
+        book.place_order(order, side).unwrap();
+        id
+    }
+
+    #[test]
+    fn cancel_all_limits_itself_to_the_filtered_outcome() {
+        let mut books = MarketBooks::new();
+        let yes_bid = rest(&mut books.yes, "alice", Side::Bid, "0.40");
+        let no_bid = rest(&mut books.no, "alice", Side::Bid, "0.40");
+        let filter = CancelFilter {
+            user_address: Some("alice".into()),
+            outcome: Some(Outcome::No),
+            ..CancelFilter::default()
+        };
+        let removed = books.cancel_all(&filter);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].id, no_bid);
+        assert!(books.yes.find_order(yes_bid).is_some());
+    }
+
+    #[test]
+    fn cancel_owned_leaves_other_users_orders_alone() {
+        let mut books = MarketBooks::new();
+        let id = rest(&mut books.yes, "alice", Side::Ask, "0.60");
+        assert!(books.cancel_owned(id, "bob").is_none());
+        assert!(books.yes.find_order(id).is_some());
+        assert_eq!(books.cancel_owned(id, "alice").map(|o| o.id), Some(id));
+        assert!(books.yes.find_order(id).is_none());
+    }
+}
@@ -1,15 +1,24 @@
 use std::collections::{BTreeMap, VecDeque};
 
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::Serialize;
 use uuid::Uuid;
 
-use crate::types::{OrderEntry, Side, Trade};
+use crate::types::{
+    Amended, BestPrices, MarketOrder, MarketOrderSize, OrderEntry, PostOnly, Quote, QuoteFill,
+    Side, Trade,
+};
+
+/// Outcome shares pay out at most 1, so no order may rest above it.
+pub const MAX_PRICE: Decimal = Decimal::ONE;
 
 #[derive(Default, Serialize, Debug)]
 pub struct OrderBook {
     pub bids: BTreeMap<Decimal, VecDeque<OrderEntry>>,
     pub asks: BTreeMap<Decimal, VecDeque<OrderEntry>>,
+    /// Sequence handed out to the last order that rested on the book.
+    #[serde(skip)]
+    next_seq: u64,
 }
 
 impl OrderBook {
@@ -25,11 +34,62 @@ impl OrderBook {
     pub fn best_ask(&self) -> Option<Decimal> {
         self.asks.keys().next().copied()
     }
+    pub fn best_prices(&self) -> BestPrices {
+        BestPrices {
+            best_bid: self.best_bid(),
+            best_ask: self.best_ask(),
+        }
+    }
+    fn next_seq(&mut self) -> u64 {
+        self.next_seq += 1;
+        self.next_seq
+    }
+    // Best opposite price an order at `price` on `side` would trade against.
+    fn crossing_price(&self, side: &Side, price: Decimal) -> Option<Decimal> {
+        match side {
+            Side::Bid => self.best_ask().filter(|ask| *ask <= price),
+            Side::Ask => self.best_bid().filter(|bid| *bid >= price),
+        }
+    }
+    // A post-only order that would cross is refused, or slid to one tick
+    // inside the best opposite price so that it rests without taking.
+    fn apply_post_only(&self, order: &mut OrderEntry, side: &Side) -> Result<(), String> {
+        let Some(post_only) = order.post_only else {
+            return Ok(());
+        };
+        let Some(opposite) = self.crossing_price(side, order.price) else {
+            return Ok(());
+        };
+        match post_only {
+            PostOnly::Reject => Err(format!(
+                "post-only order at {} would cross the book at {}",
+                order.price, opposite
+            )),
+            PostOnly::Slide { tick } => {
+                order.price = match side {
+                    Side::Bid => opposite - tick,
+                    Side::Ask => opposite + tick,
+                };
+                match side {
+                    Side::Bid if order.price <= Decimal::ZERO => Err(format!(
+                        "post-only bid can't slide below the best ask of {}",
+                        opposite
+                    )),
+                    Side::Ask if order.price > MAX_PRICE => Err(format!(
+                        "post-only ask can't slide above the best bid of {}",
+                        opposite
+                    )),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
     pub fn place_order(
         &mut self,
         mut order: OrderEntry,
         side: Side,
-    ) -> (Uuid, Vec<Trade>, Decimal) {
+    ) -> Result<(Uuid, Vec<Trade>, Decimal), String> {
+        self.apply_post_only(&mut order, &side)?;
         let mut trades: Vec<Trade> = Vec::new();
         match side {
             Side::Bid => {
@@ -49,7 +109,9 @@ impl OrderBook {
                         if let Some(maker) = queue.front_mut() {
                             let take: Decimal = order.qty.min(maker.qty);
                             maker.qty -= take;
+                            maker.filled_qty += take;
                             order.qty -= take;
+                            order.filled_qty += take;
 
                             // record the trade
                             trades.push(Trade {
@@ -58,6 +120,7 @@ impl OrderBook {
                                 price: order.price,
                                 quantity: take,
                                 market_id: maker.market_id.clone(),
+                                maker_order_id: maker.id,
                             });
 
                             // remove the maker if fully filled
@@ -78,12 +141,9 @@ impl OrderBook {
                 }
                 if order.qty > Decimal::ZERO {
                     let entry = OrderEntry {
-                        id: order.id,
-                        user_address: order.user_address.clone(),
-                        market_id: order.market_id.clone(),
                         side: Side::Bid,
-                        price: order.price,
-                        qty: order.qty,
+                        seq: self.next_seq(),
+                        ..order.clone()
                     };
                     self.bids.entry(order.price).or_default().push_back(entry);
                 }
@@ -103,7 +163,9 @@ impl OrderBook {
                         if let Some(maker) = queue.front_mut() {
                             let take: Decimal = maker.qty.min(order.qty);
                             maker.qty -= take;
+                            maker.filled_qty += take;
                             order.qty -= take;
+                            order.filled_qty += take;
 
                             trades.push(Trade {
                                 buyer_address: maker.user_address.clone(),
@@ -111,6 +173,7 @@ impl OrderBook {
                                 price: order.price,
                                 quantity: take,
                                 market_id: maker.market_id.clone(),
+                                maker_order_id: maker.id,
                             });
 
                             if maker.qty == Decimal::ZERO {
@@ -129,12 +192,9 @@ impl OrderBook {
                 }
                 if order.qty > Decimal::ZERO {
                     let entry = OrderEntry {
-                        id: order.id,
-                        user_address: order.user_address.clone(),
-                        market_id: order.market_id.clone(),
                         side: Side::Ask,
-                        price: order.price.clone(),
-                        qty: order.qty.clone(),
+                        seq: self.next_seq(),
+                        ..order.clone()
                     };
                     self.asks
                         .entry(order.price.clone())
@@ -143,7 +203,260 @@ impl OrderBook {
                 }
             }
         }
-        (order.id, trades, order.qty)
+        Ok((order.id, trades, order.qty))
+    }
+
+    // Sweep the opposite side at the makers' prices, never going past
+    // `order.price` (the worst acceptable price). Nothing is left resting;
+    // the returned remainder is in the unit of `market.size`: unfilled shares
+    // or unspent notional.
+    pub fn place_market_order(
+        &mut self,
+        mut order: OrderEntry,
+        side: Side,
+        market: MarketOrder,
+    ) -> (Uuid, Vec<Trade>, Decimal) {
+        let mut trades: Vec<Trade> = Vec::new();
+        let mut remaining = match market.size {
+            MarketOrderSize::Quantity(qty) => qty,
+            MarketOrderSize::Notional(amount) => amount,
+        };
+        let book = match side {
+            Side::Bid => &mut self.asks,
+            Side::Ask => &mut self.bids,
+        };
+        'sweep: while remaining > Decimal::ZERO {
+            let best = match side {
+                Side::Bid => book.keys().next().copied(),
+                Side::Ask => book.keys().next_back().copied(),
+            };
+            let Some(level) = best else {
+                break;
+            };
+            let within_bound = match side {
+                Side::Bid => level <= order.price,
+                Side::Ask => level >= order.price,
+            };
+            if !within_bound {
+                break;
+            }
+            let queue = book.get_mut(&level).unwrap();
+            while let Some(maker) = queue.front_mut() {
+                let wanted = match market.size {
+                    MarketOrderSize::Quantity(_) => remaining,
+                    MarketOrderSize::Notional(_) => remaining
+                        .checked_div(level)
+                        .unwrap_or(maker.qty)
+                        .round_dp_with_strategy(market.qty_scale, RoundingStrategy::ToZero),
+                };
+                let take = wanted.min(maker.qty);
+                // the notional left can't buy a single lot at this price
+                if take.is_zero() {
+                    break 'sweep;
+                }
+                maker.qty -= take;
+                maker.filled_qty += take;
+                order.filled_qty += take;
+                remaining -= match market.size {
+                    MarketOrderSize::Quantity(_) => take,
+                    MarketOrderSize::Notional(_) => take * level,
+                };
+
+                let (buyer, seller) = match side {
+                    Side::Bid => (&order.user_address, &maker.user_address),
+                    Side::Ask => (&maker.user_address, &order.user_address),
+                };
+                trades.push(Trade {
+                    buyer_address: buyer.clone(),
+                    seller_address: seller.clone(),
+                    price: level,
+                    quantity: take,
+                    market_id: maker.market_id,
+                    maker_order_id: maker.id,
+                });
+
+                if maker.qty == Decimal::ZERO {
+                    queue.pop_front();
+                }
+                if remaining <= Decimal::ZERO {
+                    break;
+                }
+            }
+            if queue.is_empty() {
+                book.remove(&level);
+            }
+        }
+        (order.id, trades, remaining.max(Decimal::ZERO))
+    }
+
+    // Simulate matching an order of `qty` without touching the book. A limit
+    // order fills at its own price, like `place_order`; without a limit each
+    // fill is priced at the maker's level.
+    pub fn quote(&self, side: &Side, limit: Option<Decimal>, qty: Decimal) -> Quote {
+        let levels: Box<dyn Iterator<Item = (&Decimal, &VecDeque<OrderEntry>)>> = match side {
+            Side::Bid => Box::new(self.asks.iter()),
+            Side::Ask => Box::new(self.bids.iter().rev()),
+        };
+        let mut remaining = qty;
+        let mut fills = Vec::new();
+        for (level, queue) in levels {
+            if remaining.is_zero() {
+                break;
+            }
+            let crosses = match (side, limit) {
+                (_, None) => true,
+                (Side::Bid, Some(limit)) => limit >= *level,
+                (Side::Ask, Some(limit)) => limit <= *level,
+            };
+            if !crosses {
+                break;
+            }
+            for maker in queue {
+                if remaining.is_zero() {
+                    break;
+                }
+                let take = remaining.min(maker.qty);
+                remaining -= take;
+                fills.push(QuoteFill {
+                    maker_order_id: maker.id,
+                    price: limit.unwrap_or(*level),
+                    quantity: take,
+                });
+            }
+        }
+
+        let filled_qty: Decimal = fills.iter().map(|f| f.quantity).sum();
+        let total_cost: Decimal = fills.iter().map(|f| f.price * f.quantity).sum();
+        let prices = fills.iter().map(|f| f.price);
+        let worst_price = match side {
+            Side::Bid => prices.max(),
+            Side::Ask => prices.min(),
+        };
+        Quote {
+            avg_price: (!filled_qty.is_zero()).then(|| total_cost / filled_qty),
+            worst_price,
+            fills,
+            filled_qty,
+            remaining_qty: remaining,
+            total_cost,
+        }
+    }
+
+    // Look up a resting order on either side of the book by id
+    pub fn find_order(&self, order_id: Uuid) -> Option<&OrderEntry> {
+        self.bids
+            .values()
+            .chain(self.asks.values())
+            .flatten()
+            .find(|o| o.id == order_id)
+    }
+
+    fn find_order_mut(&mut self, order_id: Uuid) -> Option<&mut OrderEntry> {
+        self.bids
+            .values_mut()
+            .chain(self.asks.values_mut())
+            .flatten()
+            .find(|o| o.id == order_id)
+    }
+
+    // Change the open quantity and/or price of a resting order of `user_address`.
+    // Shrinking it in place keeps its place in the queue; a new price or a
+    // larger size sends it to the back of its level, and a new price may
+    // match right away. The book is left untouched if the amend is refused.
+    pub fn amend_order(
+        &mut self,
+        order_id: Uuid,
+        user_address: &str,
+        qty: Option<Decimal>,
+        price: Option<Decimal>,
+    ) -> Result<Amended, String> {
+        let before = match self.find_order(order_id) {
+            Some(order) if order.user_address == user_address => order.clone(),
+            _ => return Err("order not found".into()),
+        };
+        let qty = qty.unwrap_or(before.qty);
+        let mut order = OrderEntry {
+            qty,
+            price: price.unwrap_or(before.price),
+            // growing the order grows its total size; shrinking leaves the
+            // original size as history
+            original_qty: before.original_qty + (qty - before.qty).max(Decimal::ZERO),
+            ..before.clone()
+        };
+        if order.qty <= Decimal::ZERO || order.price <= Decimal::ZERO {
+            return Err("price and quantity must be positive".into());
+        }
+        if order.price == before.price && order.qty <= before.qty {
+            if let Some(resting) = self.find_order_mut(order_id) {
+                resting.qty = order.qty;
+            }
+            return Ok(Amended {
+                price: order.price,
+                original_qty: order.original_qty,
+                trades: Vec::new(),
+                remaining_qty: order.qty,
+                kept_priority: true,
+                before,
+            });
+        }
+        // refuse before the order leaves the book; its own side doesn't
+        // affect whether it crosses
+        let side = before.side.clone();
+        self.apply_post_only(&mut order, &side)?;
+        self.remove_order(order_id);
+        let (price, original_qty) = (order.price, order.original_qty);
+        let (_, trades, remaining_qty) = self.place_order(order, side)?;
+        Ok(Amended {
+            before,
+            price,
+            original_qty,
+            trades,
+            remaining_qty,
+            kept_priority: false,
+        })
+    }
+
+    // Remove an order from either side of the book by id, without knowing its price
+    pub fn remove_order(&mut self, order_id: Uuid) -> Option<OrderEntry> {
+        for map in [&mut self.bids, &mut self.asks] {
+            let found = map.iter().find_map(|(price, q)| {
+                q.iter().position(|o| o.id == order_id).map(|pos| (*price, pos))
+            });
+            if let Some((price, pos)) = found {
+                let q = map.get_mut(&price).unwrap();
+                let order = q.remove(pos);
+                if q.is_empty() {
+                    map.remove(&price);
+                }
+                return order;
+            }
+        }
+        None
+    }
+
+    // Remove every resting order `remove` picks, dropping emptied levels
+    pub fn remove_where(
+        &mut self,
+        mut remove: impl FnMut(&OrderEntry) -> bool,
+    ) -> Vec<OrderEntry> {
+        let mut removed = Vec::new();
+        for map in [&mut self.bids, &mut self.asks] {
+            for queue in map.values_mut() {
+                let (gone, kept): (VecDeque<_>, VecDeque<_>) =
+                    queue.drain(..).partition(|o| remove(o));
+                removed.extend(gone);
+                *queue = kept;
+            }
+            map.retain(|_, queue| !queue.is_empty());
+        }
+        removed
+    }
+
+    // Remove every resting order from both sides of the book
+    pub fn drain(&mut self) -> Vec<OrderEntry> {
+        let bids = std::mem::take(&mut self.bids);
+        let asks = std::mem::take(&mut self.asks);
+        bids.into_values().chain(asks.into_values()).flatten().collect()
     }
 
     pub fn cancel_order(&mut self, side: Side, price: Decimal, order_id: Uuid) -> (bool, String) {
@@ -166,3 +479,219 @@ impl OrderBook {
         (false, "something went wrong".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CancelFilter;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn order(user: &str, side: Side, price: &str, qty: &str) -> OrderEntry {
+        OrderEntry::new(
+            Uuid::new_v4(),
+            user.to_string(),
+            1,
+            side,
+            dec(price),
+            dec(qty),
+            0,
+        )
+    }
+
+    fn post_only(mode: PostOnly, side: Side, price: &str, qty: &str) -> OrderEntry {
+        OrderEntry {
+            post_only: Some(mode),
+            ..order("taker", side, price, qty)
+        }
+    }
+
+    fn slide() -> PostOnly {
+        PostOnly::Slide { tick: dec("0.01") }
+    }
+
+    /// A book with a bid at 0.40 and an ask at 0.60.
+    fn spread_book() -> OrderBook {
+        let mut book = OrderBook::new();
+        book.place_order(order("maker", Side::Bid, "0.40", "10"), Side::Bid)
+            .unwrap();
+        book.place_order(order("maker", Side::Ask, "0.60", "10"), Side::Ask)
+            .unwrap();
+        book
+    }
+
+    #[test]
+    fn post_only_reject_refuses_a_crossing_order() {
+        let mut book = spread_book();
+        let bid = post_only(PostOnly::Reject, Side::Bid, "0.65", "5");
+        let id = bid.id;
+        assert!(book.place_order(bid, Side::Bid).is_err());
+        assert!(book.find_order(id).is_none());
+        assert_eq!(book.asks[&dec("0.60")][0].qty, dec("10"));
+    }
+
+    #[test]
+    fn post_only_rests_unchanged_when_it_does_not_cross() {
+        let mut book = spread_book();
+        let bid = post_only(PostOnly::Reject, Side::Bid, "0.55", "5");
+        let id = bid.id;
+        let (_, trades, rem) = book.place_order(bid, Side::Bid).unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(rem, dec("5"));
+        assert_eq!(book.find_order(id).unwrap().price, dec("0.55"));
+    }
+
+    #[test]
+    fn post_only_slide_rests_one_tick_inside_the_best_opposite_price() {
+        let mut book = spread_book();
+        let bid = post_only(slide(), Side::Bid, "0.70", "5");
+        let bid_id = bid.id;
+        let (_, trades, _) = book.place_order(bid, Side::Bid).unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(book.find_order(bid_id).unwrap().price, dec("0.59"));
+
+        let mut book = spread_book();
+        let ask = post_only(slide(), Side::Ask, "0.30", "5");
+        let ask_id = ask.id;
+        let (_, trades, _) = book.place_order(ask, Side::Ask).unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(book.find_order(ask_id).unwrap().price, dec("0.41"));
+    }
+
+    /// Two bids of 10 resting at 0.40, `first` ahead of `second`.
+    fn queued_bids() -> (OrderBook, Uuid, Uuid) {
+        let mut book = OrderBook::new();
+        let first = order("alice", Side::Bid, "0.40", "10");
+        let second = order("bob", Side::Bid, "0.40", "10");
+        let ids = (first.id, second.id);
+        book.place_order(first, Side::Bid).unwrap();
+        book.place_order(second, Side::Bid).unwrap();
+        (book, ids.0, ids.1)
+    }
+
+    fn queue_ids(book: &OrderBook, price: &str) -> Vec<Uuid> {
+        book.bids[&dec(price)].iter().map(|o| o.id).collect()
+    }
+
+    #[test]
+    fn amend_shrinking_in_place_keeps_priority() {
+        let (mut book, first, second) = queued_bids();
+        let amended = book
+            .amend_order(first, "alice", Some(dec("4")), None)
+            .unwrap();
+        assert!(amended.kept_priority);
+        assert_eq!(amended.remaining_qty, dec("4"));
+        assert_eq!(amended.original_qty, dec("10"));
+        assert_eq!(queue_ids(&book, "0.40"), vec![first, second]);
+        assert_eq!(book.find_order(first).unwrap().qty, dec("4"));
+    }
+
+    #[test]
+    fn amend_repricing_loses_priority_and_may_trade() {
+        let (mut book, first, _) = queued_bids();
+        book.place_order(order("carol", Side::Ask, "0.45", "4"), Side::Ask)
+            .unwrap();
+        let amended = book
+            .amend_order(first, "alice", None, Some(dec("0.45")))
+            .unwrap();
+        assert!(!amended.kept_priority);
+        assert_eq!(amended.trades.len(), 1);
+        assert_eq!(amended.trades[0].quantity, dec("4"));
+        assert_eq!(amended.remaining_qty, dec("6"));
+        assert_eq!(queue_ids(&book, "0.45"), vec![first]);
+        assert_eq!(book.find_order(first).unwrap().filled_qty, dec("4"));
+    }
+
+    #[test]
+    fn amend_refuses_someone_elses_order() {
+        let (mut book, first, _) = queued_bids();
+        assert!(
+            book.amend_order(first, "bob", Some(dec("1")), None)
+                .is_err()
+        );
+        assert_eq!(book.find_order(first).unwrap().qty, dec("10"));
+    }
+
+    #[test]
+    fn remove_where_takes_only_matching_orders_and_drops_empty_levels() {
+        let (mut book, first, second) = queued_bids();
+        let low = order("alice", Side::Bid, "0.30", "5");
+        let ask = order("alice", Side::Ask, "0.60", "5");
+        let (low_id, ask_id) = (low.id, ask.id);
+        book.place_order(low, Side::Bid).unwrap();
+        book.place_order(ask, Side::Ask).unwrap();
+
+        let filter = CancelFilter {
+            user_address: Some("alice".into()),
+            side: Some(Side::Bid),
+            ..CancelFilter::default()
+        };
+        let mut removed = book
+            .remove_where(|o| filter.matches(o))
+            .into_iter()
+            .map(|o| o.id)
+            .collect::<Vec<_>>();
+        removed.sort();
+        let mut expected = vec![first, low_id];
+        expected.sort();
+        assert_eq!(removed, expected);
+        assert_eq!(queue_ids(&book, "0.40"), vec![second]);
+        assert!(!book.bids.contains_key(&dec("0.30")));
+        assert!(book.find_order(ask_id).is_some());
+    }
+
+    #[test]
+    fn remove_where_respects_an_inclusive_price_range() {
+        let mut book = OrderBook::new();
+        let ids = ["0.20", "0.30", "0.40", "0.50"].map(|price| {
+            let bid = order("alice", Side::Bid, price, "1");
+            let id = bid.id;
+            book.place_order(bid, Side::Bid).unwrap();
+            id
+        });
+        let filter = CancelFilter {
+            min_price: Some(dec("0.30")),
+            max_price: Some(dec("0.40")),
+            ..CancelFilter::default()
+        };
+        let removed = book.remove_where(|o| filter.matches(o));
+        assert_eq!(removed.len(), 2);
+        assert!(book.find_order(ids[0]).is_some());
+        assert!(book.find_order(ids[1]).is_none());
+        assert!(book.find_order(ids[2]).is_none());
+        assert!(book.find_order(ids[3]).is_some());
+    }
+
+    #[test]
+    fn post_only_slide_is_refused_outside_the_price_range() {
+        let mut book = OrderBook::new();
+        book.place_order(order("maker", Side::Ask, "0.01", "10"), Side::Ask)
+            .unwrap();
+        let bid = post_only(slide(), Side::Bid, "0.50", "5");
+        let err = book.place_order(bid, Side::Bid).unwrap_err();
+        assert!(err.contains("best ask"));
+
+        let mut book = OrderBook::new();
+        book.place_order(order("maker", Side::Bid, "1", "10"), Side::Bid)
+            .unwrap();
+        let ask = post_only(slide(), Side::Ask, "0.50", "5");
+        let err = book.place_order(ask, Side::Ask).unwrap_err();
+        assert!(err.contains("best bid"));
+    }
+
+    #[test]
+    fn amend_growing_loses_priority_and_grows_the_original_size() {
+        let (mut book, first, second) = queued_bids();
+        let amended = book
+            .amend_order(first, "alice", Some(dec("15")), None)
+            .unwrap();
+        assert!(!amended.kept_priority);
+        assert_eq!(amended.original_qty, dec("15"));
+        assert_eq!(queue_ids(&book, "0.40"), vec![second, first]);
+        let resting = book.find_order(first).unwrap();
+        assert_eq!(resting.qty, dec("15"));
+        assert_eq!(resting.original_qty, dec("15"));
+    }
+}
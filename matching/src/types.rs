@@ -8,6 +8,12 @@ pub enum Side {
     Ask,
 }
 
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Yes,
+    No,
+}
+
 #[derive(Clone, Serialize, Debug)]
 pub struct OrderEntry {
     pub id: Uuid,
@@ -15,36 +21,225 @@ pub struct OrderEntry {
     pub market_id: u64,
     pub side: Side,
     pub price: Decimal,
+    /// Quantity still open.
     pub qty: Decimal,
+    /// Quantity requested when the order was placed.
+    pub original_qty: Decimal,
+    /// Quantity matched so far, across every time the order hit the book.
+    pub filled_qty: Decimal,
+    /// Unix time in milliseconds at which the order was accepted.
+    pub timestamp: i64,
+    /// Insertion sequence within its book, assigned when the order rests.
+    pub seq: u64,
+    /// Set on orders that must only add liquidity.
+    pub post_only: Option<PostOnly>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// What happens to a post-only order that would cross the best opposite price.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub enum PostOnly {
+    /// The order is refused.
+    Reject,
+    /// The order is repriced one `tick` inside the best opposite price.
+    Slide { tick: Decimal },
+}
+
+impl OrderEntry {
+    pub fn new(
+        id: Uuid,
+        user_address: String,
+        market_id: u64,
+        side: Side,
+        price: Decimal,
+        qty: Decimal,
+        timestamp: i64,
+    ) -> Self {
+        Self {
+            id,
+            user_address,
+            market_id,
+            side,
+            price,
+            qty,
+            original_qty: qty,
+            filled_qty: Decimal::ZERO,
+            timestamp,
+            seq: 0,
+            post_only: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Trade {
     pub market_id: u64,
+    pub maker_order_id: Uuid,
     pub buyer_address: String,
     pub seller_address: String,
     pub price: Decimal,
     pub quantity: Decimal,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Clone, Serialize, Debug)]
 pub struct SnapshotData {
     pub price: Decimal,
     pub quantity: Decimal,
     pub total: Decimal,
 }
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 pub struct MarketSnapshot {
     pub yes: (Vec<SnapshotData>, Vec<SnapshotData>),
     pub no: (Vec<SnapshotData>, Vec<SnapshotData>),
 }
 
+/// How much a market order should fill.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketOrderSize {
+    /// Number of shares.
+    Quantity(Decimal),
+    /// Collateral to spend (bid) or to receive (ask).
+    Notional(Decimal),
+}
+
+/// The furthest a market order may sweep from the top of the book.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceBound {
+    /// Highest price a bid pays, lowest price an ask accepts.
+    WorstPrice(Decimal),
+    /// Fraction of the best opposite price, e.g. 0.02 for 2%.
+    MaxSlippage(Decimal),
+}
+
+/// A market order's size, plus the number of decimals shares are rounded
+/// down to when sizing by notional.
+#[derive(Clone, Copy, Debug)]
+pub struct MarketOrder {
+    pub size: MarketOrderSize,
+    pub qty_scale: u32,
+}
+
+/// One maker a simulated order would match.
+#[derive(Clone, Serialize, Debug)]
+pub struct QuoteFill {
+    pub maker_order_id: Uuid,
+    pub price: Decimal,
+    pub quantity: Decimal,
+}
+
+/// What an order would do against the book right now.
+#[derive(Clone, Serialize, Debug)]
+pub struct Quote {
+    pub fills: Vec<QuoteFill>,
+    pub filled_qty: Decimal,
+    /// Quantity that would be left to rest (or go unfilled).
+    pub remaining_qty: Decimal,
+    pub avg_price: Option<Decimal>,
+    /// Highest price paid for a bid, lowest price received for an ask.
+    pub worst_price: Option<Decimal>,
+    /// Collateral paid (bid) or received (ask) for the filled quantity.
+    pub total_cost: Decimal,
+}
+
+/// Which resting orders a mass cancel removes; `None` matches everything.
+#[derive(Clone, Debug, Default)]
+pub struct CancelFilter {
+    pub user_address: Option<String>,
+    pub outcome: Option<Outcome>,
+    pub side: Option<Side>,
+    /// Inclusive price range.
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+}
+
+impl CancelFilter {
+    /// Whether `order` matches every filter other than the outcome.
+    pub fn matches(&self, order: &OrderEntry) -> bool {
+        self.user_address
+            .as_ref()
+            .is_none_or(|user| &order.user_address == user)
+            && self.side.as_ref().is_none_or(|side| &order.side == side)
+            && self.min_price.is_none_or(|min| order.price >= min)
+            && self.max_price.is_none_or(|max| order.price <= max)
+    }
+}
+
+/// A resting order as it was before an amend, and what the amend did.
+#[derive(Debug)]
+pub struct Amended {
+    pub before: OrderEntry,
+    /// Price the order now has; a sliding post-only order may differ from the
+    /// one asked for.
+    pub price: Decimal,
+    /// Total size of the order after the amend; an increase adds to it.
+    pub original_qty: Decimal,
+    /// Trades the new price matched right away.
+    pub trades: Vec<Trade>,
+    /// Quantity left resting.
+    pub remaining_qty: Decimal,
+    /// False if the order went to the back of a queue.
+    pub kept_priority: bool,
+}
+
+/// Top of one outcome's book.
+#[derive(Clone, Copy, Serialize, Debug, Default)]
+pub struct BestPrices {
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+}
+
+impl BestPrices {
+    /// Worst price an order on `side` may fill at under `bound`, or `None` if
+    /// there is nothing on the opposite side to measure slippage from.
+    pub fn worst_price(&self, side: &Side, bound: PriceBound) -> Option<Decimal> {
+        match (bound, side) {
+            (PriceBound::WorstPrice(price), _) => Some(price),
+            (PriceBound::MaxSlippage(slippage), Side::Bid) => {
+                self.best_ask.map(|ask| ask * (Decimal::ONE + slippage))
+            }
+            (PriceBound::MaxSlippage(slippage), Side::Ask) => {
+                self.best_bid.map(|bid| bid * (Decimal::ONE - slippage))
+            }
+        }
+    }
+
+    /// Midpoint of the spread, if both sides are quoted.
+    pub fn mid(&self) -> Option<Decimal> {
+        match (self.best_bid, self.best_ask) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / Decimal::TWO),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Debug)]
 pub struct OpenOrder {
     pub id: Uuid,          // Unique Order ID (UUID or On-chain ID)
     pub market_id: String,   // To verify context
-    pub outcome: String,     // "Yes" or "No"
+    pub outcome: Outcome,
     pub side: Side,        // "Bid" (Buy) or "Ask" (Sell)
     pub price: Decimal,      // Limit Price (e.g., 0.55)
-    pub quantity: Decimal,   // Quantity of shares
+    pub quantity: Decimal,   // Quantity of shares still open on the book
+    pub original_amount: Decimal, // Total size requested
+    pub filled_amount: Decimal,   // How much has matched so far
+    pub timestamp: i64,      // Creation time in unix millis (for sorting)
+    pub sequence: u64,       // Insertion order within the outcome's book
+}
+
+impl OpenOrder {
+    pub fn from_entry(order: &OrderEntry, market_id: &str, outcome: Outcome) -> Self {
+        Self {
+            id: order.id,
+            market_id: market_id.to_string(),
+            outcome,
+            side: order.side.clone(),
+            price: order.price,
+            quantity: order.qty,
+            original_amount: order.original_qty,
+            filled_amount: order.filled_qty,
+            timestamp: order.timestamp,
+            sequence: order.seq,
+        }
+    }
 }
\ No newline at end of file